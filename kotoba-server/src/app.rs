@@ -1,15 +1,197 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tokio::runtime::Runtime;
+
+use crate::dict::{self, Dict};
+use crate::history::History;
+use crate::inflection;
+use crate::search;
+use crate::segment;
+
+/// Environment variable that overrides the data directory.
+const DATA_DIR_VAR: &str = "KOTOBA_DATA";
+
+/// Environment variable that overrides the store directory, where user data
+/// such as the word history is saved.
+const STORE_DIR_VAR: &str = "KOTOBA_STORE";
+
+/// Default store directory, relative to the working directory.
+const STORE_DEFAULT_DIR: &str = "./.store";
+
 /// Shared application state.
-pub struct App {}
+pub struct App {
+	dict: Dict,
+	inflection_rules: inflection::Rules,
+	search_index: search::Index,
+	search_cache: search::Cache,
+	history: History,
+	runtime: Runtime,
+}
 
 impl App {
 	/// Initializes the application state and returns the static [App] instance.
 	pub fn get() -> &'static App {
 		lazy_static! {
 			static ref APP: App = {
-				let app = App {};
-				app
+				let source_dir = data_dir().join("source");
+				let dict = match Dict::load(&source_dir) {
+					Ok(dict) => dict,
+					Err(err) => {
+						eprintln!(
+							"err: loading dictionary from {}: {}",
+							source_dir.display(),
+							err
+						);
+						Dict::default()
+					}
+				};
+				let rules_file = data_dir().join("grammar").join("deinflect.json");
+				let inflection_rules = match inflection::Rules::load(&rules_file) {
+					Ok(rules) => rules,
+					Err(err) => {
+						eprintln!(
+							"err: loading inflection rules from {}: {}",
+							rules_file.display(),
+							err
+						);
+						inflection::Rules::default()
+					}
+				};
+				let search_index = search::Index::build(dict.entries());
+				let history = match History::load(&store_dir()) {
+					Ok(history) => history,
+					Err(err) => {
+						eprintln!(
+							"err: loading word history from {}: {}",
+							store_dir().display(),
+							err
+						);
+						History::default()
+					}
+				};
+				let runtime = tokio::runtime::Builder::new_multi_thread()
+					.thread_name("kotoba-worker")
+					.enable_all()
+					.build()
+					.expect("failed to start the background runtime");
+				App {
+					dict,
+					inflection_rules,
+					search_index,
+					search_cache: Default::default(),
+					history,
+					runtime,
+				}
 			};
 		}
 		&APP
 	}
+
+	/// Dictionary data.
+	pub fn dict(&self) -> &Dict {
+		&self.dict
+	}
+
+	/// Looks up the dictionary entries for the de-inflected forms of the word.
+	pub fn deinflect(&self, word: &str) -> Vec<inflection::Match<'_>> {
+		inflection::deinflect(&self.dict, &self.inflection_rules, word)
+	}
+
+	/// Conjugation table for a verb or adjective entry.
+	pub fn conjugate(&self, entry: &dict::Entry) -> Vec<inflection::Inflection> {
+		self.inflection_rules.conjugate(entry)
+	}
+
+	/// Looks up the dictionary entries for all the words in a phrase.
+	pub fn deinflect_all(&self, phrase: &str) -> Vec<inflection::Match<'_>> {
+		inflection::deinflect_all(&self.dict, &self.inflection_rules, phrase)
+	}
+
+	/// Splits the text into dictionary words.
+	pub fn segment(&self, text: &str) -> Vec<segment::Segment<'_>> {
+		segment::segment(&self.dict, &self.inflection_rules, text)
+	}
+
+	/// Looks up the dictionary words starting at the offset of the text.
+	pub fn scan(&self, text: &str, offset: usize, max_length: usize) -> segment::Scan<'_> {
+		segment::scan(&self.dict, &self.inflection_rules, text, offset, max_length)
+	}
+
+	/// Search engine for the dictionary.
+	pub fn search(&self) -> search::Engine<'_> {
+		let history: HashSet<usize> = self
+			.history
+			.list()
+			.iter()
+			.filter_map(|(id, _)| self.dict.index_of(id))
+			.collect();
+		search::Engine::new(&self.dict, &self.search_index, Arc::new(history))
+	}
+
+	/// Returns the search for the query. Searches are cached and run in the
+	/// background, so this returns immediately.
+	pub fn search_for(
+		&'static self,
+		query: &str,
+	) -> Result<Arc<search::Search>, search::ParseError> {
+		let expr = search::parse(query)?;
+		let search = self.search_cache.get(&expr, || {
+			search::Search::spawn(self.runtime.handle(), self.search(), query, expr.clone())
+		});
+		Ok(search)
+	}
+
+	/// Word history with the saved entries.
+	pub fn history(&self) -> &History {
+		&self.history
+	}
+
+	/// Saves the entry to the word history. Returns false if the entry does
+	/// not exist.
+	pub fn insert_history(&self, id: &str) -> io::Result<bool> {
+		if self.dict.get(id).is_none() {
+			return Ok(false);
+		}
+		self.history.insert(id)?;
+		self.history_changed();
+		Ok(true)
+	}
+
+	/// Removes the entry from the word history. Returns false if the entry
+	/// was not saved.
+	pub fn remove_history(&self, id: &str) -> io::Result<bool> {
+		let removed = self.history.remove(id)?;
+		if removed {
+			self.history_changed();
+		}
+		Ok(removed)
+	}
+
+	/// Flushes cached searches that depend on the word history. Must be called
+	/// when the history changes.
+	pub fn history_changed(&self) {
+		self.search_cache.remove_history();
+	}
+}
+
+/// Root directory for the application data. Defaults to the `data` directory
+/// in the repository.
+fn data_dir() -> PathBuf {
+	match std::env::var_os(DATA_DIR_VAR) {
+		Some(dir) => PathBuf::from(dir),
+		None => PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+			.join("..")
+			.join("data"),
+	}
+}
+
+/// Directory for the user data.
+fn store_dir() -> PathBuf {
+	match std::env::var_os(STORE_DIR_VAR) {
+		Some(dir) => PathBuf::from(dir),
+		None => PathBuf::from(STORE_DEFAULT_DIR),
+	}
 }
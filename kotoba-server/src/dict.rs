@@ -0,0 +1,572 @@
+//! In-memory dictionary data.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::kana;
+
+mod breakdown;
+mod entry;
+mod frequency;
+mod jlpt;
+mod jmdict;
+mod kanji;
+mod kanjidic;
+mod kradfile;
+mod pitch;
+
+pub use breakdown::*;
+pub use entry::*;
+pub use kanji::*;
+
+/// Source file for the JMdict English dictionary.
+const JMDICT_FILE: &str = "jmdict_english.zip";
+
+/// Source file for the KANJIDIC2 kanji dictionary.
+const KANJIDIC_FILE: &str = "kanjidic2.zip";
+
+/// Source file for the pitch accents.
+const PITCH_FILE: &str = "accents.txt";
+
+/// Source file for the JLPT levels.
+const JLPT_FILE: &str = "jlpt.json";
+
+/// Source file for the KRADFILE kanji decomposition. This is the `kradzip.zip`
+/// archive from the EDRDG, see `kradzip.md` in the `data/source` directory.
+const KRADFILE_FILE: &str = "kradzip.zip";
+
+type ComponentMap = HashMap<String, Vec<String>>;
+
+/// Dictionary entries and related data.
+#[derive(Default)]
+pub struct Dict {
+	entries: Vec<Entry>,
+	by_sequence: HashMap<String, usize>,
+	by_expr: HashMap<String, Vec<usize>>,
+	by_hiragana: HashMap<String, Vec<usize>>,
+	tags: HashMap<String, String>,
+	kanji: Vec<Kanji>,
+	kanji_by_literal: HashMap<String, usize>,
+	components: ComponentMap,
+	used_in: ComponentMap,
+}
+
+impl Dict {
+	/// Loads the dictionary from the source files in the given directory.
+	///
+	/// Only the JMdict source is required. Failures loading the other sources
+	/// are logged and the related data is left empty.
+	pub fn load(source_dir: &Path) -> io::Result<Dict> {
+		let start = Instant::now();
+		let import = jmdict::import_entries(&source_dir.join(JMDICT_FILE))?;
+		println!(
+			"inf: loaded {} entries and {} tags from {} in {:.2?}",
+			import.entries.len(),
+			import.tags.len(),
+			JMDICT_FILE,
+			start.elapsed()
+		);
+
+		let start = Instant::now();
+		let pitch_file = source_dir.join(PITCH_FILE);
+		let pitch = optional(&pitch_file, pitch::import_pitch(&pitch_file));
+		let mut entries = import.entries;
+		let count = pitch::merge_pitch(&mut entries, &pitch);
+		println!(
+			"inf: merged {} pitch accents for {} words from {} in {:.2?}",
+			count,
+			pitch.len(),
+			PITCH_FILE,
+			start.elapsed()
+		);
+
+		let start = Instant::now();
+		let frequency = optional(source_dir, frequency::import_frequency(source_dir));
+		let ranked = frequency::apply_frequency(&mut entries, &frequency);
+		println!(
+			"inf: loaded frequency for {} words and {} chars, ranked {} of {} entries in {:.2?}",
+			frequency.words.len(),
+			frequency.chars.len(),
+			ranked,
+			entries.len(),
+			start.elapsed()
+		);
+
+		let start = Instant::now();
+		let jlpt_file = source_dir.join(JLPT_FILE);
+		let (jlpt, invalid) = optional(&jlpt_file, jlpt::import_jlpt(&jlpt_file));
+		let count = jlpt::apply_jlpt(&mut entries, &jlpt);
+		println!(
+			"inf: merged {} JLPT words from {} in {:.2?} ({} invalid lines skipped)",
+			count,
+			JLPT_FILE,
+			start.elapsed(),
+			invalid
+		);
+
+		// Entries are kept sorted by their global position, which puts popular
+		// entries first, then sorts by frequency and otherwise preserves the
+		// source order.
+		entries.sort_by(|a, b| {
+			let popular = b.popular().cmp(&a.popular());
+			popular.then_with(|| frequency::compare_frequency(a, b))
+		});
+		for (index, entry) in entries.iter_mut().enumerate() {
+			entry.position = index + 1;
+		}
+
+		let start = Instant::now();
+		let kanji_file = source_dir.join(KANJIDIC_FILE);
+		let mut kanji = optional(&kanji_file, kanjidic::import_kanji(&kanji_file));
+		for it in kanji.iter_mut() {
+			it.frequency = frequency.chars.get(&it.literal).cloned();
+		}
+		let kanji_jlpt = jlpt::apply_kanji_jlpt(&mut kanji, &jlpt);
+		println!(
+			"inf: loaded {} kanji ({} with JLPT level) from {} in {:.2?}",
+			kanji.len(),
+			kanji_jlpt,
+			KANJIDIC_FILE,
+			start.elapsed()
+		);
+		let kanji_by_literal = kanji
+			.iter()
+			.enumerate()
+			.map(|(index, kanji)| (kanji.literal.clone(), index))
+			.collect();
+
+		let start = Instant::now();
+		let kradfile = source_dir.join(KRADFILE_FILE);
+		let components = optional(&kradfile, kradfile::import_components(&kradfile));
+		let (components, used_in) = map_components(components);
+		println!(
+			"inf: loaded {} kanji components from {} in {:.2?}",
+			components.len(),
+			KRADFILE_FILE,
+			start.elapsed()
+		);
+
+		let mut dict = Dict {
+			entries,
+			tags: import.tags,
+			kanji,
+			kanji_by_literal,
+			components,
+			used_in,
+			..Default::default()
+		};
+		dict.index_entries();
+		Ok(dict)
+	}
+
+	/// Creates a dictionary with just the given entries, which must be sorted
+	/// by position.
+	#[cfg(test)]
+	pub fn from_entries(entries: Vec<Entry>) -> Dict {
+		let mut dict = Dict {
+			entries,
+			..Default::default()
+		};
+		dict.index_entries();
+		dict
+	}
+
+	/// Builds the lookup maps for the entries.
+	fn index_entries(&mut self) {
+		let entries = &self.entries;
+		self.by_sequence = entries
+			.iter()
+			.enumerate()
+			.map(|(index, entry)| (entry.sequence.clone(), index))
+			.collect();
+		// Since entries are sorted, the indexes for each expression are also
+		// sorted by position.
+		let mut by_expr: HashMap<String, Vec<usize>> = HashMap::new();
+		let mut by_hiragana: HashMap<String, Vec<usize>> = HashMap::new();
+		for (index, entry) in entries.iter().enumerate() {
+			let kanji = entry.kanji.iter().map(|x| &x.expr);
+			let reading = entry.reading.iter().map(|x| &x.expr);
+			for expr in kanji.chain(reading) {
+				let list = by_expr.entry(expr.clone()).or_default();
+				if list.last() != Some(&index) {
+					list.push(index);
+				}
+				// Only expressions with katakana have a different hiragana
+				// form, so we skip the conversion for everything else.
+				if expr.chars().any(is_katakana) {
+					let list = by_hiragana.entry(kana::to_hiragana(expr)).or_default();
+					if list.last() != Some(&index) {
+						list.push(index);
+					}
+				}
+			}
+		}
+		self.by_expr = by_expr;
+		self.by_hiragana = by_hiragana;
+	}
+
+	/// All entries in the dictionary, sorted by their position.
+	pub fn entries(&self) -> &[Entry] {
+		&self.entries
+	}
+
+	/// Entries for the JLPT level, sorted by frequency. Entries without
+	/// frequency information come last, sorted by position.
+	pub fn words_by_jlpt(&self, level: u8) -> Vec<&Entry> {
+		let mut list: Vec<&Entry> = self
+			.entries
+			.iter()
+			.filter(|x| x.jlpt == Some(level))
+			.collect();
+		list.sort_by(|a, b| frequency::compare_frequency(a, b));
+		list
+	}
+
+	/// Retrieves an entry by its sequence number.
+	pub fn get(&self, sequence: &str) -> Option<&Entry> {
+		self.by_sequence
+			.get(sequence)
+			.map(|&index| &self.entries[index])
+	}
+
+	/// Index of the entry in [Dict::entries] by its sequence number.
+	pub fn index_of(&self, sequence: &str) -> Option<usize> {
+		self.by_sequence.get(sequence).cloned()
+	}
+
+	/// Retrieves entries by their sequence number. The result is in the same
+	/// order as the input, skipping sequences not found.
+	pub fn get_list<S: AsRef<str>>(&self, sequences: &[S]) -> Vec<&Entry> {
+		sequences
+			.iter()
+			.filter_map(|x| self.get(x.as_ref()))
+			.collect()
+	}
+
+	/// Returns all entries with a kanji or reading element exactly matching
+	/// the given expression, sorted by position.
+	pub fn by_expr(&self, expr: &str) -> Vec<&Entry> {
+		match self.by_expr.get(expr) {
+			Some(list) => list.iter().map(|&index| &self.entries[index]).collect(),
+			None => Vec::new(),
+		}
+	}
+
+	/// Returns all entries with a kanji or reading element matching the given
+	/// expression either exactly or by its hiragana form, sorted by position.
+	pub fn exact(&self, expr: &str) -> Vec<&Entry> {
+		let by_expr = self.by_expr.get(expr).into_iter().flatten();
+		let by_hiragana = self.by_hiragana.get(expr).into_iter().flatten();
+		let mut list: Vec<usize> = by_expr.chain(by_hiragana).cloned().collect();
+		list.sort_unstable();
+		list.dedup();
+		list.into_iter().map(|index| &self.entries[index]).collect()
+	}
+
+	/// Strict lookup of entries by an exact kanji and reading pair.
+	///
+	/// An empty kanji, or one that is the same as the reading, will match only
+	/// kana-only entries.
+	///
+	/// Entries where the pair are the primary kanji and reading come first,
+	/// then other entries by position.
+	pub fn lookup(&self, kanji: &str, reading: &str) -> Vec<&Entry> {
+		let has_kanji = !kanji.is_empty() && kanji != reading;
+		let mut entries: Vec<&Entry> = self
+			.by_expr(reading)
+			.into_iter()
+			.filter(|entry| {
+				if !entry.reading.iter().any(|x| x.expr == reading) {
+					false
+				} else if has_kanji {
+					entry.kanji.iter().any(|x| x.expr == kanji)
+				} else {
+					entry.kanji.is_empty()
+				}
+			})
+			.collect();
+		entries.sort_by_key(|entry| {
+			let primary = entry.read() == reading && (!has_kanji || entry.word() == kanji);
+			!primary
+		});
+		entries
+	}
+
+	/// All kanji in the dictionary.
+	pub fn kanji(&self) -> &[Kanji] {
+		&self.kanji
+	}
+
+	/// Retrieves a kanji by its character.
+	pub fn get_kanji(&self, literal: &str) -> Option<&Kanji> {
+		self.kanji_by_literal
+			.get(literal)
+			.map(|&index| &self.kanji[index])
+	}
+
+	/// Returns all kanji matching the filter, sorted by frequency.
+	pub fn search_kanji(&self, filter: &KanjiFilter) -> Vec<&Kanji> {
+		let mut list: Vec<&Kanji> = self
+			.kanji
+			.iter()
+			.filter(|x| filter.matches(x, self.kanji_components(&x.literal)))
+			.collect();
+		sort_kanji(&mut list);
+		list
+	}
+
+	/// Components of the kanji from the KRADFILE decomposition.
+	pub fn kanji_components(&self, literal: &str) -> &[String] {
+		self.components
+			.get(literal)
+			.map(|x| &x[..])
+			.unwrap_or_default()
+	}
+
+	/// Kanji having the component in their decomposition, sorted by
+	/// frequency.
+	pub fn kanji_used_in(&self, component: &str) -> Vec<&Kanji> {
+		let list = self
+			.used_in
+			.get(component)
+			.map(|x| &x[..])
+			.unwrap_or_default();
+		let mut list: Vec<&Kanji> = list.iter().filter_map(|x| self.get_kanji(x)).collect();
+		sort_kanji(&mut list);
+		list
+	}
+
+	/// Narrows down the kanji having all the selected components.
+	///
+	/// Returns the matching kanji sorted by frequency and the components that
+	/// can still be selected to narrow down the result. Those are sorted by
+	/// stroke count.
+	///
+	/// With no components selected, this returns no kanji and all components.
+	pub fn search_components<S: AsRef<str>>(&self, selected: &[S]) -> (Vec<&Kanji>, Vec<&str>) {
+		let selected: Vec<&str> = selected.iter().map(|x| x.as_ref()).collect();
+		let (kanji, mut components): (Vec<&Kanji>, Vec<&str>) = match selected.split_first() {
+			None => (
+				Vec::new(),
+				self.used_in.keys().map(|x| x.as_str()).collect(),
+			),
+			Some((first, rest)) => {
+				let mut kanji = self.kanji_used_in(first);
+				kanji.retain(|x| {
+					let components = self.kanji_components(&x.literal);
+					rest.iter().all(|it| components.iter().any(|x| x == it))
+				});
+				let mut components: Vec<&str> = kanji
+					.iter()
+					.flat_map(|x| self.kanji_components(&x.literal))
+					.map(|x| x.as_str())
+					.filter(|x| !selected.contains(x))
+					.collect();
+				components.sort_unstable();
+				components.dedup();
+				(kanji, components)
+			}
+		};
+		components.sort_by_key(|&x| {
+			(
+				self.get_kanji(x).map(|x| x.stroke()).unwrap_or(usize::MAX),
+				x,
+			)
+		});
+		(kanji, components)
+	}
+
+	/// Map of all tags names to their descriptions.
+	pub fn tags(&self) -> &HashMap<String, String> {
+		&self.tags
+	}
+
+	/// Returns the description for a tag.
+	///
+	/// This also supports the priority tags, which are not declared in the
+	/// source file.
+	pub fn tag_text(&self, name: &str) -> String {
+		if let Some(text) = self.tags.get(name) {
+			return text.clone();
+		}
+		let text = match name {
+			"news1" => "top half 12K entries from Mainichi Shimbun newspaper word corpus",
+			"news2" => "bottom half 12K entries from Mainichi Shimbun newspaper word corpus",
+			"ichi1" => "appears in the \"Ichimango goi bunruishuu\" word corpus",
+			"ichi2" => "appears in the \"Ichimango goi bunruishuu\" word corpus, but demoted due to low frequency on other sources",
+			"spec1" => "top half of common words that do not appear on the word corpus",
+			"spec2" => "bottom half of common words that do not appear on the word corpus",
+			"gai1" => "top half of common loanwords in the word corpus",
+			"gai2" => "bottom half of common loanwords in the word corpus",
+			_ => "",
+		};
+		if !text.is_empty() {
+			return text.to_string();
+		}
+		match name
+			.strip_prefix("nf")
+			.and_then(|x| x.parse::<usize>().ok())
+		{
+			Some(page) if page > 1 => format!("top {}K in the word corpus", page as f64 * 0.5),
+			Some(page) => format!("top {} in the word corpus", page * 500),
+			None => String::new(),
+		}
+	}
+}
+
+/// Returns the value for an optional source, logging the error and using the
+/// default value if it failed to load.
+fn optional<T: Default>(filename: &Path, result: io::Result<T>) -> T {
+	result.unwrap_or_else(|err| {
+		eprintln!("err: loading {}: {}", filename.display(), err);
+		T::default()
+	})
+}
+
+/// Maps each kanji to its components and each component to the kanji using
+/// it, from the KRADFILE decomposition.
+fn map_components(list: Vec<(String, Vec<String>)>) -> (ComponentMap, ComponentMap) {
+	let components: ComponentMap = list.into_iter().collect();
+	let mut used_in: ComponentMap = HashMap::new();
+	for (kanji, list) in components.iter() {
+		for it in list {
+			used_in.entry(it.clone()).or_default().push(kanji.clone());
+		}
+	}
+	(components, used_in)
+}
+
+/// Sorts kanji by frequency. Kanji without frequency information come after,
+/// sorted by their ranking and then by the source order.
+fn sort_kanji(list: &mut Vec<&Kanji>) {
+	list.sort_by(|a, b| {
+		let frequency = |x: &Kanji| x.frequency.unwrap_or_default();
+		let ranking = |x: &Kanji| x.ranking.unwrap_or(usize::MAX);
+		frequency(b)
+			.partial_cmp(&frequency(a))
+			.unwrap_or(Ordering::Equal)
+			.then(ranking(a).cmp(&ranking(b)))
+	});
+}
+
+fn is_katakana(chr: char) -> bool {
+	matches!(chr, '\u{30A1}'..='\u{30FA}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9D}')
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn kanji_dict(kanji: Vec<Kanji>, components: Vec<(String, Vec<String>)>) -> Dict {
+		let kanji_by_literal = kanji
+			.iter()
+			.enumerate()
+			.map(|(index, kanji)| (kanji.literal.clone(), index))
+			.collect();
+		let (components, used_in) = map_components(components);
+		Dict {
+			kanji,
+			kanji_by_literal,
+			components,
+			used_in,
+			..Default::default()
+		}
+	}
+
+	fn literals(list: &[&Kanji]) -> Vec<String> {
+		list.iter().map(|x| x.literal.clone()).collect()
+	}
+
+	#[test]
+	fn searches_components() {
+		let kanji = |literal: &str, ranking: usize| Kanji {
+			literal: literal.to_string(),
+			ranking: Some(ranking),
+			..Default::default()
+		};
+		let decomposition = |literal: &str, list: &[&str]| {
+			let list = list.iter().map(|x| x.to_string()).collect();
+			(literal.to_string(), list)
+		};
+		let dict = kanji_dict(
+			vec![kanji("亜", 2), kanji("唖", 3), kanji("口", 1)],
+			vec![
+				decomposition("亜", &["｜", "一", "口"]),
+				decomposition("唖", &["｜", "一", "口", "二"]),
+				decomposition("口", &["口"]),
+			],
+		);
+
+		let (list, components) = dict.search_components(&["口"]);
+		assert_eq!(literals(&list), vec!["口", "亜", "唖"]);
+		assert_eq!(components.len(), 3);
+
+		let (list, components) = dict.search_components(&["口", "二"]);
+		assert_eq!(literals(&list), vec!["唖"]);
+		assert_eq!(components.len(), 2);
+		assert!(!components.contains(&"二"));
+
+		let (list, components) = dict.search_components(&["口", "x"]);
+		assert!(list.is_empty() && components.is_empty());
+
+		let (list, components) = dict.search_components::<&str>(&[]);
+		assert!(list.is_empty());
+		assert_eq!(components.len(), 4);
+	}
+
+	#[test]
+	fn searches_kanji_by_radicals() {
+		let kanji = |literal: &str, ranking: usize| Kanji {
+			literal: literal.to_string(),
+			ranking: Some(ranking),
+			stroke_count: vec![7],
+			..Default::default()
+		};
+		let decomposition = |literal: &str, list: &[&str]| {
+			let list = list.iter().map(|x| x.to_string()).collect();
+			(literal.to_string(), list)
+		};
+		let dict = kanji_dict(
+			vec![kanji("亜", 2), kanji("唖", 3), kanji("口", 1)],
+			vec![
+				decomposition("亜", &["｜", "一", "口"]),
+				decomposition("唖", &["｜", "一", "口", "二"]),
+				decomposition("口", &["口"]),
+			],
+		);
+		let search = |radicals: &[&str]| {
+			let filter = KanjiFilter {
+				radicals: radicals.iter().map(|x| x.to_string()).collect(),
+				..Default::default()
+			};
+			literals(&dict.search_kanji(&filter))
+		};
+		assert_eq!(search(&["口"]), vec!["口", "亜", "唖"]);
+		assert_eq!(search(&["口", "一"]), vec!["亜", "唖"]);
+		assert_eq!(search(&["二", "口", "｜"]), vec!["唖"]);
+		assert!(search(&["口", "木"]).is_empty());
+	}
+
+	#[test]
+	#[ignore = "requires data/source/kradzip.zip"]
+	fn searches_components_on_source_data() {
+		let source_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../data/source");
+		let kanji = kanjidic::import_kanji(&source_dir.join(KANJIDIC_FILE)).unwrap();
+		let components = kradfile::import_components(&source_dir.join(KRADFILE_FILE)).unwrap();
+		let dict = kanji_dict(kanji, components);
+
+		let (all, _) = dict.search_components(&["口"]);
+		let (list, components) = dict.search_components(&["口", "木"]);
+		assert!(!list.is_empty() && list.len() < all.len());
+		assert!(literals(&list).contains(&String::from("困")));
+		for it in list {
+			let components = dict.kanji_components(&it.literal);
+			assert!(components.iter().any(|x| x == "口"));
+			assert!(components.iter().any(|x| x == "木"));
+		}
+		assert!(!components.contains(&"口") && !components.contains(&"木"));
+	}
+}
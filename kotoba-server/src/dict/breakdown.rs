@@ -0,0 +1,393 @@
+use crate::kana;
+
+use super::{Dict, Entry, Kanji};
+
+/// Kanji from an entry word, with the reading used in the word.
+#[derive(Clone, Debug)]
+pub struct KanjiBreakdown<'a> {
+	/// Character in the entry word.
+	pub character: String,
+
+	/// Kanji dictionary data for the character, if available.
+	pub kanji: Option<&'a Kanji>,
+
+	/// Part of the entry reading (in hiragana) for the character. This is
+	/// [None] if the reading could not be aligned with a kanji reading, which
+	/// is the case for irregular readings (e.g. `今日`).
+	pub reading: Option<String>,
+
+	/// Kanji reading used in the word, as listed in the kanji dictionary
+	/// (e.g. `ガク` or `まな.ぶ`).
+	pub source: Option<String>,
+
+	/// Type of the kanji reading: `on`, `kun`, or `nanori`.
+	pub kind: Option<String>,
+
+	/// The reading is voiced by rendaku (e.g. `か` to `が`).
+	pub rendaku: bool,
+
+	/// The last mora of the reading is geminated into a small `っ` (e.g.
+	/// `がく` to `がっ`).
+	pub gemination: bool,
+}
+
+/// Cost for a kanji that could not be aligned to any of its readings.
+const COST_UNKNOWN: f64 = 1.0;
+
+/// Cost for using a name reading.
+const COST_NANORI: f64 = 0.5;
+
+/// Cost for a kun reading with modified okurigana.
+const COST_OKURIGANA: f64 = 0.2;
+
+/// Cost for each sound change (rendaku and gemination).
+const COST_SOUND_CHANGE: f64 = 0.1;
+
+/// Iteration mark, repeating the previous kanji.
+const REPEAT_MARK: char = '々';
+
+/// Candidate reading for a character.
+#[derive(Clone, Debug)]
+struct Candidate {
+	text: Vec<char>,
+	source: String,
+	kind: &'static str,
+	cost: f64,
+	rendaku: bool,
+	gemination: bool,
+}
+
+impl Dict {
+	/// Breaks down the kanji in the entry word, mapping each to the kanji
+	/// reading used in the word.
+	///
+	/// The mapping is done by aligning the entry reading against the on, kun
+	/// and name readings of each kanji, considering rendaku and small-tsu
+	/// gemination. Kana in the word must match the reading exactly.
+	pub fn kanji_breakdown(&self, entry: &Entry) -> Vec<KanjiBreakdown<'_>> {
+		let kanji = match entry.kanji.first() {
+			Some(kanji) => &kanji.expr,
+			None => return Vec::new(),
+		};
+		let reading = entry
+			.reading
+			.iter()
+			.find(|x| !x.no_kanji && (x.restrict.is_empty() || x.restrict.contains(kanji)))
+			.map(|x| x.expr.as_str())
+			.unwrap_or_else(|| entry.read());
+
+		let word: Vec<char> = kanji.chars().collect();
+		let mut candidates = Vec::with_capacity(word.len());
+		for (index, &chr) in word.iter().enumerate() {
+			let list = if kana::is_kana(chr) {
+				Vec::new()
+			} else if chr == REPEAT_MARK && index > 0 {
+				self.get_kanji(&word[index - 1].to_string())
+					.map(candidates_for)
+					.unwrap_or_default()
+			} else {
+				self.get_kanji(&chr.to_string())
+					.map(candidates_for)
+					.unwrap_or_default()
+			};
+			candidates.push(list);
+		}
+
+		let reading: Vec<char> = kana::to_hiragana(reading).chars().collect();
+		let steps = align(&word, &reading, &candidates);
+		word.iter()
+			.zip(steps)
+			.filter(|(chr, _)| !kana::is_kana(**chr))
+			.map(|(chr, step)| {
+				let character = chr.to_string();
+				let (reading, candidate) = match step {
+					Some((text, candidate)) => (Some(text), Some(candidate)),
+					None => (None, None),
+				};
+				KanjiBreakdown {
+					kanji: self.get_kanji(&character),
+					character,
+					reading,
+					source: candidate.as_ref().map(|x| x.source.clone()),
+					kind: candidate.as_ref().map(|x| x.kind.to_string()),
+					rendaku: candidate.as_ref().map(|x| x.rendaku).unwrap_or_default(),
+					gemination: candidate.as_ref().map(|x| x.gemination).unwrap_or_default(),
+				}
+			})
+			.collect()
+	}
+}
+
+/// Base reading candidates for the kanji, in hiragana.
+fn candidates_for(kanji: &Kanji) -> Vec<Candidate> {
+	let new = |text: &str, source: &str, kind: &'static str, cost: f64| Candidate {
+		text: kana::to_hiragana(text).chars().collect(),
+		source: source.to_string(),
+		kind,
+		cost,
+		rendaku: false,
+		gemination: false,
+	};
+
+	let mut output = Vec::new();
+	for on in kanji.readings("ja_on") {
+		output.push(new(on.trim_matches('-'), on, "on", 0.0));
+	}
+	for kun in kanji.readings("ja_kun") {
+		let text = kun.trim_matches('-');
+		let mut parts = text.splitn(2, '.');
+		let stem = parts.next().unwrap_or_default();
+		output.push(new(stem, kun, "kun", 0.0));
+		if let Some(okurigana) = parts.next() {
+			// Okurigana may be omitted from the word, in which case it is part
+			// of the kanji reading (e.g. `話` as `はなし` from `はな.す`).
+			let full = format!("{}{}", stem, okurigana);
+			output.push(new(&full, kun, "kun", COST_OKURIGANA));
+			if let Some(stem) = to_i_row(&full) {
+				output.push(new(&stem, kun, "kun", COST_OKURIGANA));
+			}
+		}
+	}
+	for nanori in kanji.nanori.iter() {
+		output.push(new(nanori, nanori, "nanori", COST_NANORI));
+	}
+	output.retain(|x| !x.text.is_empty());
+	output
+}
+
+/// Applies the sound changes to the candidate for the position in the word.
+fn variants(candidate: &Candidate, is_first: bool, is_last: bool) -> Vec<Candidate> {
+	let mut output = vec![candidate.clone()];
+	if !is_first {
+		let first = candidate.text[0];
+		let marks = [kana::MARK_VOICED, kana::MARK_SEMI_VOICED];
+		for voiced in marks.iter().filter_map(|&mark| kana::compose(first, mark)) {
+			let mut it = candidate.clone();
+			it.text[0] = voiced;
+			it.rendaku = true;
+			it.cost += COST_SOUND_CHANGE;
+			output.push(it);
+		}
+	}
+	if !is_last && candidate.text.len() > 1 {
+		let last = candidate.text.len() - 1;
+		let base: Vec<Candidate> = output.clone();
+		for it in base {
+			if "つくちき".contains(it.text[last]) {
+				let mut it = it;
+				it.text[last] = 'っ';
+				it.gemination = true;
+				it.cost += COST_SOUND_CHANGE;
+				output.push(it);
+			}
+		}
+	}
+	output
+}
+
+/// Converts the final u-row kana of a verb reading to the i-row, which is
+/// the form used by nouns derived from verbs.
+fn to_i_row(text: &str) -> Option<String> {
+	const U_ROW: &str = "うくぐすつぬぶむる";
+	const I_ROW: &str = "いきぎしちにびみり";
+	let last = text.chars().last()?;
+	let index = U_ROW.chars().position(|x| x == last)?;
+	let prefix = &text[..text.len() - last.len_utf8()];
+	Some(format!("{}{}", prefix, I_ROW.chars().nth(index)?))
+}
+
+/// Aligns the word with the reading using the candidate readings for each
+/// character. Returns for each character the part of the reading and the
+/// candidate used, or [None] for kana and characters that could not be
+/// aligned.
+fn align(
+	word: &[char],
+	reading: &[char],
+	candidates: &[Vec<Candidate>],
+) -> Vec<Option<(String, Candidate)>> {
+	#[derive(Clone)]
+	enum Step {
+		Kana,
+		Reading(Candidate),
+		Unknown(usize),
+	}
+	type Table = Vec<Vec<Option<(f64, Step, usize)>>>;
+
+	// Lowest cost to align the suffixes of the word and reading, with the
+	// step taken and the length of reading consumed.
+	let (n, m) = (word.len(), reading.len());
+	let mut best: Table = vec![vec![None; m + 1]; n + 1];
+	best[n][m] = Some((0.0, Step::Kana, 0));
+	let cost = |best: &Table, i: usize, j: usize| best[i][j].as_ref().map(|x| x.0);
+
+	for i in (0..n).rev() {
+		let chr = word[i];
+		for j in (0..m).rev() {
+			let mut current: Option<(f64, Step, usize)> = None;
+			let mut push = |value: f64, step: Step, len: usize| {
+				if current.as_ref().map(|x| value < x.0).unwrap_or(true) {
+					current = Some((value, step, len));
+				}
+			};
+
+			if kana::is_kana(chr) {
+				if kana::to_hiragana(&chr.to_string()).starts_with(reading[j]) {
+					if let Some(value) = cost(&best, i + 1, j + 1) {
+						push(value, Step::Kana, 1);
+					}
+				}
+			} else {
+				for candidate in candidates[i].iter() {
+					for it in variants(candidate, i == 0, i == n - 1) {
+						let len = it.text.len();
+						if reading[j..].starts_with(&it.text) {
+							if let Some(value) = cost(&best, i + 1, j + len) {
+								push(value + it.cost, Step::Reading(it), len);
+							}
+						}
+					}
+				}
+
+				// Irregular readings are assigned to a whole run of kanji. The
+				// cost is the same for any run length, so that jukujikun (e.g.
+				// 大人 as おとな) are not split to match part of the reading.
+				let run = word[i..].iter().take_while(|x| !kana::is_kana(**x)).count();
+				for count in 1..=run {
+					for len in 1..=m - j {
+						if let Some(value) = cost(&best, i + count, j + len) {
+							push(value + COST_UNKNOWN, Step::Unknown(count), len);
+						}
+					}
+				}
+			}
+			best[i][j] = current;
+		}
+	}
+
+	let mut output = vec![None; n];
+	let (mut i, mut j) = (0, 0);
+	while i < n {
+		let (_, step, len) = match &best[i][j] {
+			Some(step) => step.clone(),
+			None => break,
+		};
+		let text: String = reading[j..j + len].iter().collect();
+		match step {
+			Step::Kana => i += 1,
+			Step::Reading(candidate) => {
+				output[i] = Some((text, candidate));
+				i += 1;
+			}
+			Step::Unknown(count) => i += count,
+		}
+		j += len;
+	}
+	output
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn candidate(text: &str, kind: &'static str) -> Candidate {
+		Candidate {
+			text: text.chars().collect(),
+			source: text.to_string(),
+			kind,
+			cost: 0.0,
+			rendaku: false,
+			gemination: false,
+		}
+	}
+
+	fn check(word: &str, reading: &str, candidates: &[&[&str]], expected: &[&str]) {
+		let word: Vec<char> = word.chars().collect();
+		let reading: Vec<char> = reading.chars().collect();
+		let candidates: Vec<Vec<Candidate>> = candidates
+			.iter()
+			.map(|list| list.iter().map(|x| candidate(x, "on")).collect())
+			.collect();
+		let output: Vec<String> = align(&word, &reading, &candidates)
+			.into_iter()
+			.map(|x| x.map(|x| x.0).unwrap_or_default())
+			.collect();
+		assert_eq!(output, expected);
+	}
+
+	#[test]
+	fn aligns_readings() {
+		check(
+			"漢字",
+			"かんじ",
+			&[&["かん"], &["じ", "あざ"]],
+			&["かん", "じ"],
+		);
+		check(
+			"食べる",
+			"たべる",
+			&[&["しょく", "た"], &[], &[]],
+			&["た", "", ""],
+		);
+	}
+
+	#[test]
+	fn aligns_sound_changes() {
+		check(
+			"学校",
+			"がっこう",
+			&[&["がく"], &["こう"]],
+			&["がっ", "こう"],
+		);
+		check("手紙", "てがみ", &[&["て"], &["かみ"]], &["て", "がみ"]);
+		check(
+			"人々",
+			"ひとびと",
+			&[&["ひと"], &["ひと"]],
+			&["ひと", "びと"],
+		);
+	}
+
+	#[test]
+	fn skips_irregular_readings() {
+		check(
+			"今日",
+			"きょう",
+			&[&["こん", "いま"], &["にち", "ひ"]],
+			&["", ""],
+		);
+		check(
+			"今日は",
+			"きょうは",
+			&[&["こん"], &["にち"], &[]],
+			&["", "", ""],
+		);
+		check(
+			"大人気",
+			"だいにんき",
+			&[&["だい"], &[], &["き"]],
+			&["だい", "", "き"],
+		);
+
+		// The name reading お matches part of おとな, but the whole word is
+		// still a single irregular reading.
+		let nanori = Candidate {
+			cost: COST_NANORI,
+			..candidate("お", "nanori")
+		};
+		let word: Vec<char> = "大人".chars().collect();
+		let reading: Vec<char> = "おとな".chars().collect();
+		let candidates = vec![
+			vec![candidate("だい", "on"), candidate("おお", "kun"), nanori],
+			vec![candidate("じん", "on"), candidate("ひと", "kun")],
+		];
+		let output = align(&word, &reading, &candidates);
+		assert!(output.iter().all(|x| x.is_none()));
+	}
+
+	#[test]
+	fn converts_okurigana_to_i_row() {
+		assert_eq!(to_i_row("はなす").as_deref(), Some("はなし"));
+		assert_eq!(to_i_row("ひかる").as_deref(), Some("ひかり"));
+		assert_eq!(to_i_row("たかい"), None);
+	}
+}
@@ -0,0 +1,255 @@
+use crate::kana;
+
+/// Dictionary entry loaded from JMdict.
+///
+/// This directly correlates to the `<entry>` element in the XML source. See
+/// `jmdict_english.md` in the `data/source` directory for details.
+#[derive(Clone, Debug, Default)]
+pub struct Entry {
+	/// Unique numeric sequence number for the entry (`ent_seq`).
+	pub sequence: String,
+
+	/// Kanji elements, if any (`k_ele`).
+	pub kanji: Vec<EntryKanji>,
+
+	/// Reading elements for the entry (`r_ele`).
+	pub reading: Vec<EntryReading>,
+
+	/// Translational equivalents and related information (`sense`).
+	pub sense: Vec<EntrySense>,
+
+	/// Relative rank of the entry based on frequency information, if
+	/// available.
+	pub rank: Option<usize>,
+
+	/// Relative frequency count for the entry, if available.
+	pub frequency: Option<f64>,
+
+	/// Global position of the entry across the dictionary (1-based). This is
+	/// the default sort order.
+	pub position: usize,
+
+	/// JLPT level for the entry (1-5), if available.
+	pub jlpt: Option<u8>,
+}
+
+impl Entry {
+	/// Expression for the first kanji element if available, or the first
+	/// reading otherwise.
+	pub fn word(&self) -> &str {
+		match self.kanji.first() {
+			Some(kanji) => &kanji.expr,
+			None => self.read(),
+		}
+	}
+
+	/// The first reading for the entry.
+	pub fn read(&self) -> &str {
+		self.reading.first().map(|x| x.expr.as_str()).unwrap_or("")
+	}
+
+	/// Glossary from all senses joined together.
+	pub fn text(&self) -> String {
+		let senses: Vec<String> = self
+			.sense
+			.iter()
+			.map(|sense| {
+				let glossary: Vec<&str> = sense.glossary.iter().map(|x| x.text.as_str()).collect();
+				glossary.join(", ")
+			})
+			.collect();
+		senses.join(" | ")
+	}
+
+	/// True if any kanji or reading element is popular.
+	pub fn popular(&self) -> bool {
+		self.kanji.iter().any(|x| x.popular()) || self.reading.iter().any(|x| x.popular())
+	}
+
+	/// True if the entry has one of the given grammar tags, as used by the
+	/// de-inflection rules.
+	///
+	/// Tags are also matched by prefix, because the entries have tags like
+	/// `v5u` while the rules are simply `v5`.
+	pub fn has_rule_tag(&self, tags: &[String]) -> bool {
+		let has = |src: &[String]| {
+			src.iter()
+				.any(|name| tags.iter().any(|tag| name.starts_with(tag.as_str())))
+		};
+		self.kanji.iter().any(|x| has(&x.info))
+			|| self.reading.iter().any(|x| has(&x.info))
+			|| self.sense.iter().any(|x| has(&x.pos))
+	}
+}
+
+/// Kanji element for an [Entry] (`k_ele`).
+#[derive(Clone, Debug, Default)]
+pub struct EntryKanji {
+	/// Word or short phrase written using at least one non-kana character
+	/// (`keb`).
+	pub expr: String,
+
+	/// Tags related to the orthography of `expr` (`ke_inf`).
+	pub info: Vec<String>,
+
+	/// Priority tags for the element (`ke_pri`).
+	pub priority: Vec<String>,
+}
+
+impl EntryKanji {
+	/// True if the element has one of the popular priority tags.
+	pub fn popular(&self) -> bool {
+		is_popular(&self.priority)
+	}
+}
+
+/// Reading element for an [Entry] (`r_ele`).
+#[derive(Clone, Debug, Default)]
+pub struct EntryReading {
+	/// Reading restricted to kana and related characters (`reb`).
+	pub expr: String,
+
+	/// The reading is not a true reading of the kanji (`re_nokanji`).
+	pub no_kanji: bool,
+
+	/// Subset of kanji elements the reading applies to (`re_restr`).
+	pub restrict: Vec<String>,
+
+	/// Tags pertaining to the specific reading (`re_inf`).
+	pub info: Vec<String>,
+
+	/// Priority tags for the element (`re_pri`).
+	pub priority: Vec<String>,
+
+	/// Pitch accent information for the reading.
+	pub pitches: Vec<EntryPitch>,
+}
+
+impl EntryReading {
+	/// True if the element has one of the popular priority tags.
+	pub fn popular(&self) -> bool {
+		is_popular(&self.priority)
+	}
+
+	/// Number of morae in the reading.
+	pub fn mora(&self) -> usize {
+		kana::count_mora(&self.expr)
+	}
+}
+
+/// Pitch accent for an [EntryReading].
+#[derive(Clone, Debug, Default)]
+pub struct EntryPitch {
+	/// Position of the accent in the reading morae. Zero is the flat pattern.
+	pub value: usize,
+
+	/// Part-of-speech tags the pitch is restricted to, if any.
+	pub tags: Vec<String>,
+
+	/// Number of morae in the reading.
+	pub mora: usize,
+}
+
+impl EntryPitch {
+	/// Accent class for the pitch: `heiban` (flat), `atamadaka` (accent on
+	/// the first mora), `nakadaka` (accent in the middle), or `odaka` (accent
+	/// on the last mora, dropping on a following particle).
+	pub fn accent(&self) -> &'static str {
+		match self.value {
+			0 => "heiban",
+			1 => "atamadaka",
+			value if value >= self.mora => "odaka",
+			_ => "nakadaka",
+		}
+	}
+}
+
+/// Sense element for an [Entry] (`sense`).
+#[derive(Clone, Debug, Default)]
+pub struct EntrySense {
+	/// Kanji elements the sense is restricted to (`stagk`).
+	pub stag_kanji: Vec<String>,
+
+	/// Reading elements the sense is restricted to (`stagr`).
+	pub stag_reading: Vec<String>,
+
+	/// Part-of-speech tags (`pos`).
+	pub pos: Vec<String>,
+
+	/// Cross-references to related entries (`xref`).
+	pub xref: Vec<String>,
+
+	/// Antonyms for the entry (`ant`).
+	pub antonym: Vec<String>,
+
+	/// Field of application tags (`field`).
+	pub field: Vec<String>,
+
+	/// Other relevant tags (`misc`).
+	pub misc: Vec<String>,
+
+	/// Free text information about the sense (`s_inf`).
+	pub info: Vec<String>,
+
+	/// Regional dialect tags (`dial`).
+	pub dialect: Vec<String>,
+
+	/// Source language information for loan-words (`lsource`).
+	pub source: Vec<EntrySenseSource>,
+
+	/// Translational equivalents for the sense (`gloss`).
+	pub glossary: Vec<EntrySenseGlossary>,
+}
+
+/// Language source for an [EntrySense] (`lsource`).
+#[derive(Clone, Debug, Default)]
+pub struct EntrySenseSource {
+	/// Source word or phrase, if available.
+	pub text: String,
+
+	/// ISO 639-2 language code (`xml:lang` attribute).
+	pub lang: String,
+
+	/// The source partially describes the loan-word (`ls_type` attribute).
+	pub partial: bool,
+
+	/// The word was constructed from words in the source language
+	/// (`ls_wasei` attribute).
+	pub wasei: bool,
+}
+
+/// Glossary element for an [EntrySense] (`gloss`).
+#[derive(Clone, Debug, Default)]
+pub struct EntrySenseGlossary {
+	/// Text for the glossary entry.
+	pub text: String,
+
+	/// Type of the glossary (`g_type` attribute).
+	pub kind: Option<GlossaryType>,
+}
+
+/// Possible values for [EntrySenseGlossary::kind].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GlossaryType {
+	Literal,
+	Figurative,
+	Explanation,
+}
+
+impl GlossaryType {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			GlossaryType::Literal => "literal",
+			GlossaryType::Figurative => "figurative",
+			GlossaryType::Explanation => "explanation",
+		}
+	}
+}
+
+/// Entries with the news1, ichi1, spec1, spec2 and gai1 priority tags are
+/// considered popular.
+fn is_popular(priority: &[String]) -> bool {
+	priority
+		.iter()
+		.any(|x| matches!(x.as_str(), "news1" | "ichi1" | "spec1" | "spec2" | "gai1"))
+}
@@ -0,0 +1,194 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use crate::files::{invalid_data, read_zip, read_zip_all};
+
+use super::entry::Entry;
+
+/// Source file for the Innocent Corpus frequencies.
+const INNOCENT_FILE: &str = "innocent_corpus.zip";
+
+/// Source file for the Worldlex frequencies.
+const WORLDLEX_FILE: &str = "Jap.Freq.2.zip";
+
+/// Combined frequency information for words and characters.
+///
+/// Each value is the sum of the counts per million for the Innocent Corpus
+/// and the Worldlex blog, news and twitter corpora.
+#[derive(Default)]
+pub struct Frequency {
+	pub words: HashMap<String, f64>,
+	pub chars: HashMap<String, f64>,
+}
+
+/// Imports the word and character frequencies from the source directory.
+pub fn import_frequency(source_dir: &Path) -> io::Result<Frequency> {
+	let mut output = Frequency::default();
+	import_innocent_corpus(&source_dir.join(INNOCENT_FILE), &mut output)?;
+	import_worldlex(&source_dir.join(WORLDLEX_FILE), &mut output)?;
+	Ok(output)
+}
+
+/// Sets the frequency and rank for the entries. Returns the number of ranked
+/// entries.
+///
+/// The frequency of an entry is the maximum frequency of its kanji elements,
+/// or of its readings for kana-only entries, since a reading is ambiguous
+/// between many words. Entries are ranked by frequency, with ties kept in the
+/// source order.
+pub fn apply_frequency(entries: &mut [Entry], frequency: &Frequency) -> usize {
+	for entry in entries.iter_mut() {
+		let list: Vec<&String> = if entry.kanji.is_empty() {
+			entry.reading.iter().map(|x| &x.expr).collect()
+		} else {
+			entry.kanji.iter().map(|x| &x.expr).collect()
+		};
+		let value = list
+			.into_iter()
+			.filter_map(|x| frequency.words.get(x))
+			.fold(0.0, |acc: f64, &x| acc.max(x));
+		entry.frequency = if value > 0.0 { Some(value) } else { None };
+		entry.rank = None;
+	}
+
+	let mut ranked: Vec<usize> = (0..entries.len())
+		.filter(|&index| entries[index].frequency.is_some())
+		.collect();
+	ranked.sort_by(|&a, &b| compare_frequency(&entries[a], &entries[b]));
+	for (rank, &index) in ranked.iter().enumerate() {
+		entries[index].rank = Some(rank + 1);
+	}
+	ranked.len()
+}
+
+/// Compares entries by descending frequency, with entries without frequency
+/// information last.
+pub fn compare_frequency(a: &Entry, b: &Entry) -> Ordering {
+	let a = a.frequency.unwrap_or_default();
+	let b = b.frequency.unwrap_or_default();
+	b.partial_cmp(&a).unwrap_or(Ordering::Equal)
+}
+
+/// Imports the Innocent Corpus, which is split into `term_meta_bank_N.json`
+/// and `kanji_meta_bank_N.json` files with `[entry, "freq", count]` rows.
+fn import_innocent_corpus(filename: &Path, output: &mut Frequency) -> io::Result<()> {
+	let files = read_zip_all(filename, |name| {
+		name.ends_with(".json") && (name.starts_with("term_meta") || name.starts_with("kanji_meta"))
+	})?;
+
+	let mut words = Vec::new();
+	let mut chars = Vec::new();
+	for (name, data) in files {
+		let rows: Vec<(String, String, f64)> = serde_json::from_slice(&data)
+			.map_err(|err| invalid_data(format!("parsing {}: {}", name, err)))?;
+		let list = if name.starts_with("kanji_meta") {
+			&mut chars
+		} else {
+			&mut words
+		};
+		list.extend(rows.into_iter().map(|(entry, _, count)| (entry, count)));
+	}
+
+	add_per_million(&mut output.words, &words);
+	add_per_million(&mut output.chars, &chars);
+	Ok(())
+}
+
+/// Adds the counts per million for the rows, relative to their total count.
+fn add_per_million(output: &mut HashMap<String, f64>, rows: &[(String, f64)]) {
+	let total: f64 = rows.iter().map(|x| x.1).sum();
+	let millions = total / 1_000_000.0;
+	for (entry, count) in rows {
+		output.insert(entry.clone(), count / millions);
+	}
+}
+
+/// Imports the Worldlex `Jap.Freq.2.txt` and `Jap.Char.Freq.2.txt` files.
+///
+/// Those are tab-separated with a header line. The counts per million for
+/// the blog, twitter and news corpora are the 3rd, 7th and 11th columns.
+fn import_worldlex(filename: &Path, output: &mut Frequency) -> io::Result<()> {
+	let words = read_zip(filename, "Jap.Freq.2.txt")?;
+	let chars = read_zip(filename, "Jap.Char.Freq.2.txt")?;
+	add_worldlex(&mut output.words, &String::from_utf8_lossy(&words));
+	add_worldlex(&mut output.chars, &String::from_utf8_lossy(&chars));
+	Ok(())
+}
+
+fn add_worldlex(output: &mut HashMap<String, f64>, text: &str) {
+	for line in text.lines().skip(1) {
+		let fields: Vec<&str> = line.split('\t').collect();
+		if fields.len() < 13 {
+			continue;
+		}
+		let count = [2, 6, 10]
+			.iter()
+			.map(|&index| fields[index].trim().parse::<f64>())
+			.collect::<Result<Vec<_>, _>>();
+		// Some rows have invalid numbers, and are ignored.
+		if let Ok(count) = count {
+			*output.entry(fields[0].to_string()).or_default() += count.iter().sum::<f64>();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::dict::{EntryKanji, EntryReading};
+
+	fn entry(kanji: &[&str], reading: &[&str]) -> Entry {
+		Entry {
+			kanji: kanji
+				.iter()
+				.map(|x| EntryKanji {
+					expr: x.to_string(),
+					..Default::default()
+				})
+				.collect(),
+			reading: reading
+				.iter()
+				.map(|x| EntryReading {
+					expr: x.to_string(),
+					..Default::default()
+				})
+				.collect(),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn applies_frequency() {
+		let mut frequency = Frequency::default();
+		frequency.words.insert("a".to_string(), 1.0);
+		frequency.words.insert("b".to_string(), 3.0);
+		frequency.words.insert("x".to_string(), 9.0);
+		frequency.words.insert("y".to_string(), 2.0);
+
+		let mut entries = vec![
+			entry(&["a", "b"], &["x"]),
+			entry(&["c"], &["x"]),
+			entry(&[], &["y", "z"]),
+			entry(&["a"], &[]),
+		];
+		assert_eq!(apply_frequency(&mut entries, &frequency), 3);
+
+		let frequency: Vec<Option<f64>> = entries.iter().map(|x| x.frequency).collect();
+		assert_eq!(frequency, vec![Some(3.0), None, Some(2.0), Some(1.0)]);
+		let rank: Vec<Option<usize>> = entries.iter().map(|x| x.rank).collect();
+		assert_eq!(rank, vec![Some(1), None, Some(2), Some(3)]);
+	}
+
+	#[test]
+	fn adds_worldlex_counts() {
+		let mut output = HashMap::new();
+		let text = "Word\tBF\tBFPm\tBCD\tBCDPc\tTF\tTFPm\tTCD\tTCDPc\tNF\tNFPm\tNCD\tNCDPc\n\
+			の\t1\t1.5\t1\t1\t1\t2.0\t1\t1\t1\t0.5\t1\t1\n\
+			x\t1\tNA\t1\t1\t1\t2.0\t1\t1\t1\t0.5\t1\t1\n";
+		add_worldlex(&mut output, text);
+		assert_eq!(output.len(), 1);
+		assert_eq!(output["の"], 4.0);
+	}
+}
@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::files::invalid_data;
+use crate::kana;
+
+use super::entry::Entry;
+use super::kanji::Kanji;
+
+/// JLPT levels, in the order they are applied. Entries listed in more than
+/// one level end up with the highest level (e.g. N5 over N4).
+const ENTRY_LEVELS: &[u8] = &[1, 2, 3, 4, 5];
+
+/// JLPT levels for the kanji, in the order they are applied.
+const KANJI_LEVELS: &[u8] = &[5, 4, 3, 2, 1];
+
+/// Kanji and vocabulary for a single JLPT level.
+#[derive(Default)]
+pub struct JlptLevel {
+	pub kanji: Vec<String>,
+	pub vocab: Vec<JlptVocab>,
+}
+
+/// Vocabulary entry for a JLPT level.
+#[derive(Debug, PartialEq)]
+pub struct JlptVocab {
+	/// Kanji forms for the word. Empty for words listed only by reading.
+	pub terms: Vec<String>,
+
+	/// Readings for the word.
+	pub reads: Vec<String>,
+
+	/// Some entries are manually mapped to a sequence in the dictionary. A
+	/// sequence of `0` means the word is not in the dictionary.
+	pub sequence: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LevelRaw {
+	kanji: Vec<String>,
+	vocab: Vec<String>,
+}
+
+/// Imports the `jlpt.json` file. Returns the levels from 1 to 5 and the
+/// number of vocabulary lines that could not be parsed.
+pub fn import_jlpt(filename: &Path) -> io::Result<(HashMap<u8, JlptLevel>, usize)> {
+	let text = fs::read_to_string(filename)?;
+	let data: HashMap<String, LevelRaw> = serde_json::from_str(&text)
+		.map_err(|err| invalid_data(format!("parsing JLPT data: {}", err)))?;
+
+	let mut output = HashMap::new();
+	let mut invalid = 0;
+	for level in 1..=5u8 {
+		let raw = data
+			.get(&level.to_string())
+			.ok_or_else(|| invalid_data(format!("missing JLPT level {}", level)))?;
+		let mut vocab = Vec::with_capacity(raw.vocab.len());
+		for line in raw.vocab.iter() {
+			match parse_vocab(line) {
+				Some(it) => vocab.push(it),
+				None => invalid += 1,
+			}
+		}
+		let kanji = raw.kanji.clone();
+		output.insert(level, JlptLevel { kanji, vocab });
+	}
+	Ok((output, invalid))
+}
+
+/// Sets the JLPT level for the entries. Returns the number of vocabulary
+/// words that matched an entry.
+///
+/// Words are matched by their kanji forms, using the reading only when the
+/// kanji is ambiguous. Words without kanji are matched by reading. Ambiguous
+/// words that match more than one entry are ignored.
+pub fn apply_jlpt(entries: &mut [Entry], levels: &HashMap<u8, JlptLevel>) -> usize {
+	let mut by_sequence = HashMap::new();
+	let mut by_term: HashMap<&str, Vec<usize>> = HashMap::new();
+	let mut by_read: HashMap<&str, Vec<usize>> = HashMap::new();
+	for (index, entry) in entries.iter().enumerate() {
+		by_sequence.insert(entry.sequence.clone(), index);
+
+		// The main term is usually the kanji, but for kana-only entries we
+		// index the reading.
+		let terms: Vec<&str> = if entry.kanji.is_empty() {
+			entry.reading.iter().map(|x| x.expr.as_str()).collect()
+		} else {
+			entry.kanji.iter().map(|x| x.expr.as_str()).collect()
+		};
+		for term in terms {
+			add_index(&mut by_term, term, index);
+		}
+		for reading in entry.reading.iter() {
+			add_index(&mut by_read, &reading.expr, index);
+		}
+	}
+
+	let mut matches: Vec<(usize, u8)> = Vec::new();
+	let mut count = 0;
+	for &level in ENTRY_LEVELS {
+		let vocab = levels.get(&level).map(|x| &x.vocab[..]).unwrap_or_default();
+		for row in vocab {
+			if let Some(sequence) = &row.sequence {
+				if let Some(&index) = by_sequence.get(sequence) {
+					matches.push((index, level));
+					count += 1;
+				}
+				continue;
+			}
+
+			let mut ids: Vec<usize> = Vec::new();
+			if !row.terms.is_empty() {
+				// If the term has kanji we always use it, otherwise we would get
+				// false positives from homophones.
+				for term in row.terms.iter() {
+					let mut list = by_term.get(term.as_str()).cloned().unwrap_or_default();
+					if list.len() > 1 {
+						// Try to match the reading for ambiguous kanji.
+						let has_reading = |&index: &usize| {
+							let entry = &entries[index];
+							entry.reading.iter().any(|x| row.reads.contains(&x.expr))
+						};
+						let filtered: Vec<usize> =
+							list.iter().cloned().filter(has_reading).collect();
+						if !filtered.is_empty() {
+							list = filtered;
+						}
+					}
+					if list.len() == 1 {
+						ids.extend(list);
+					}
+				}
+			} else {
+				for read in row.reads.iter() {
+					match by_read.get(read.as_str()) {
+						Some(list) if list.len() == 1 => ids.extend(list),
+						_ => {}
+					}
+				}
+			}
+
+			if !ids.is_empty() {
+				count += 1;
+				matches.extend(ids.into_iter().map(|index| (index, level)));
+			}
+		}
+	}
+
+	for (index, level) in matches {
+		entries[index].jlpt = Some(level);
+	}
+	count
+}
+
+/// Sets the JLPT level for the kanji. Returns the number of kanji found.
+pub fn apply_kanji_jlpt(kanji: &mut [Kanji], levels: &HashMap<u8, JlptLevel>) -> usize {
+	let by_literal: HashMap<String, usize> = kanji
+		.iter()
+		.enumerate()
+		.map(|(index, it)| (it.literal.clone(), index))
+		.collect();
+	let mut count = 0;
+	for &level in KANJI_LEVELS {
+		let list = levels.get(&level).map(|x| &x.kanji[..]).unwrap_or_default();
+		for literal in list {
+			if let Some(&index) = by_literal.get(literal) {
+				kanji[index].jlpt = Some(level);
+				count += 1;
+			}
+		}
+	}
+	count
+}
+
+fn add_index<'a>(map: &mut HashMap<&'a str, Vec<usize>>, key: &'a str, index: usize) {
+	let list = map.entry(key).or_default();
+	if list.last() != Some(&index) {
+		list.push(index);
+	}
+}
+
+/// Parses a vocabulary line in the format `kanji: reading`, where each side
+/// may have multiple forms separated by `/` or spaces. Lines in the format
+/// `sequence! : reading` are mapped directly to a dictionary entry.
+///
+/// Returns [None] for invalid lines.
+fn parse_vocab(line: &str) -> Option<JlptVocab> {
+	let mut parts = line.splitn(2, ": ");
+	let term = parts.next().unwrap_or_default();
+	let read = parts.next().unwrap_or_default();
+
+	let mut sequence = None;
+	let mut term = term;
+	if let Some(index) = term.find('!') {
+		sequence = Some(term[..index].trim().to_string());
+		term = term[index + 1..].trim();
+	}
+
+	let split = |text: &str| -> Vec<String> {
+		let list = text.split(|x: char| x.is_whitespace() || x == '/');
+		list.filter(|x| !x.is_empty()).map(String::from).collect()
+	};
+	let mut terms = split(term);
+	let mut reads = split(read);
+
+	let is_word = |x: &String| x.chars().all(char::is_alphanumeric);
+	if !terms.iter().chain(reads.iter()).all(is_word) {
+		return None;
+	}
+	if terms.is_empty() && reads.is_empty() {
+		return None;
+	}
+
+	// Check for a kana-only entry.
+	if reads.is_empty() {
+		reads = std::mem::take(&mut terms);
+	}
+
+	// For some entries the term and reading are duplicated.
+	if reads == terms {
+		terms.clear();
+	}
+
+	if !reads.iter().all(|x| kana::is_kana_text(x)) {
+		return None;
+	}
+
+	Some(JlptVocab {
+		terms,
+		reads,
+		sequence,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn vocab(terms: &[&str], reads: &[&str], sequence: Option<&str>) -> Option<JlptVocab> {
+		Some(JlptVocab {
+			terms: terms.iter().map(|x| x.to_string()).collect(),
+			reads: reads.iter().map(|x| x.to_string()).collect(),
+			sequence: sequence.map(String::from),
+		})
+	}
+
+	#[test]
+	fn parses_vocab() {
+		assert_eq!(parse_vocab("会う: あう"), vocab(&["会う"], &["あう"], None));
+		assert_eq!(
+			parse_vocab("青い / 蒼い: あおい"),
+			vocab(&["青い", "蒼い"], &["あおい"], None)
+		);
+		assert_eq!(parse_vocab("ああ: "), vocab(&[], &["ああ"], None));
+		assert_eq!(parse_vocab("あの: あの"), vocab(&[], &["あの"], None));
+		assert_eq!(
+			parse_vocab("1000430! : あの"),
+			vocab(&[], &["あの"], Some("1000430"))
+		);
+	}
+
+	#[test]
+	fn skips_invalid_vocab() {
+		assert_eq!(parse_vocab("会う: a-u"), None);
+		assert_eq!(parse_vocab("会う: 会う"), None);
+		assert_eq!(parse_vocab(": "), None);
+	}
+}
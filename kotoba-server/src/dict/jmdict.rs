@@ -0,0 +1,452 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::files::{invalid_data, read_zip};
+
+use super::entry::*;
+
+/// Result of importing the JMdict source file.
+pub struct Import {
+	/// Entries in the same order as the source file.
+	pub entries: Vec<Entry>,
+
+	/// Tags declared as entities in the source file, mapping the tag name to
+	/// its description (e.g. `hob` to `Hokkaido-ben`).
+	pub tags: HashMap<String, String>,
+}
+
+/// This tag is present on some entries but is not declared.
+const TAG_UNCLASSIFIED: &str = "unc";
+
+/// Imports the entries from the `jmdict_english.zip` file.
+pub fn import_entries(filename: &Path) -> io::Result<Import> {
+	let data = read_zip(filename, "data.xml")?;
+	parse(&data)
+}
+
+/// Parses the JMdict XML data.
+fn parse(data: &[u8]) -> io::Result<Import> {
+	let mut reader = Reader::from_reader(data);
+	reader.expand_empty_elements(true);
+
+	let mut import = Import {
+		entries: Vec::new(),
+		tags: HashMap::new(),
+	};
+
+	// Entities are used to encode the tags (e.g. `<pos>&n;</pos>`). We want to
+	// preserve the entity name as the tag.
+	let mut entities: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+	entities.insert(TAG_UNCLASSIFIED.into(), TAG_UNCLASSIFIED.into());
+
+	let mut ctx = Context::default();
+	let mut buf = Vec::new();
+	loop {
+		let event = reader.read_event(&mut buf).map_err(|err| {
+			invalid_data(format!(
+				"parsing XML at {}: {}",
+				reader.buffer_position(),
+				err
+			))
+		})?;
+		match event {
+			Event::DocType(ref text) => {
+				let text = String::from_utf8_lossy(text.escaped());
+				for (name, label) in parse_entities(&text) {
+					entities.insert(name.clone().into_bytes(), name.clone().into_bytes());
+					import.tags.insert(name, label);
+				}
+			}
+			Event::Start(ref tag) => {
+				ctx.open_tag(tag)?;
+			}
+			Event::End(ref tag) => {
+				let tag = String::from_utf8_lossy(tag.name()).to_string();
+				ctx.close_tag(&tag, &import.tags, &mut import.entries)?;
+			}
+			Event::Text(ref text) => {
+				let text = text
+					.unescaped_with_custom_entities(&entities)
+					.map_err(|err| {
+						invalid_data(format!("invalid text {}: {}", ctx.at_pos(), err))
+					})?;
+				ctx.text.push_str(&String::from_utf8_lossy(&text));
+			}
+			Event::Eof => break,
+			_ => {}
+		}
+		buf.clear();
+	}
+
+	Ok(import)
+}
+
+/// Parses the `<!ENTITY hob "Hokkaido-ben">` declarations from the DOCTYPE.
+fn parse_entities(doctype: &str) -> Vec<(String, String)> {
+	const PREFIX: &str = "<!ENTITY";
+	let mut out = Vec::new();
+	let mut text = doctype;
+	while let Some(index) = text.find(PREFIX) {
+		text = &text[index + PREFIX.len()..];
+		let decl = match text.find('>') {
+			Some(end) => &text[..end],
+			None => break,
+		};
+		let mut parts = decl.trim().splitn(2, char::is_whitespace);
+		let name = parts.next().unwrap_or_default();
+		let label = parts.next().unwrap_or_default().trim().trim_matches('"');
+		if !name.is_empty() && !label.is_empty() {
+			out.push((name.to_string(), label.to_string()));
+		}
+	}
+	out
+}
+
+/// Parsing context for the XML elements.
+#[derive(Default)]
+struct Context {
+	tags: Vec<String>,
+	text: String,
+
+	cur_entry: Option<Entry>,
+	cur_kanji: Option<EntryKanji>,
+	cur_reading: Option<EntryReading>,
+	cur_sense: Option<EntrySense>,
+	cur_source: Option<EntrySenseSource>,
+	cur_glossary: Option<EntrySenseGlossary>,
+}
+
+impl Context {
+	fn open_tag(&mut self, tag: &BytesStart) -> io::Result<()> {
+		let name = String::from_utf8_lossy(tag.name()).to_string();
+		self.text.clear();
+
+		match name.as_str() {
+			"entry" => self.cur_entry = Some(Entry::default()),
+			"k_ele" => self.cur_kanji = Some(EntryKanji::default()),
+			"r_ele" => self.cur_reading = Some(EntryReading::default()),
+			"re_nokanji" => self.reading()?.no_kanji = true,
+			"sense" => self.cur_sense = Some(EntrySense::default()),
+			"lsource" => {
+				let mut source = EntrySenseSource {
+					lang: String::from("eng"),
+					..Default::default()
+				};
+				for attr in tag.attributes() {
+					let attr =
+						attr.map_err(|err| invalid_data(format!("{} {}", err, self.at_pos())))?;
+					let value = String::from_utf8_lossy(&attr.value).to_string();
+					match attr.key {
+						b"xml:lang" => source.lang = value,
+						b"ls_type" => source.partial = value == "part",
+						b"ls_wasei" => source.wasei = !value.is_empty(),
+						_ => {}
+					}
+				}
+				self.cur_source = Some(source);
+			}
+			"gloss" => {
+				let mut glossary = EntrySenseGlossary::default();
+				for attr in tag.attributes() {
+					let attr =
+						attr.map_err(|err| invalid_data(format!("{} {}", err, self.at_pos())))?;
+					if attr.key == b"g_type" {
+						glossary.kind = match &attr.value[..] {
+							b"lit" => Some(GlossaryType::Literal),
+							b"fig" => Some(GlossaryType::Figurative),
+							b"expl" => Some(GlossaryType::Explanation),
+							_ => None,
+						};
+					}
+				}
+				self.cur_glossary = Some(glossary);
+			}
+			_ => {}
+		}
+
+		self.tags.push(name);
+		Ok(())
+	}
+
+	fn close_tag(
+		&mut self,
+		tag: &str,
+		tags: &HashMap<String, String>,
+		entries: &mut Vec<Entry>,
+	) -> io::Result<()> {
+		let text = self.text.split_whitespace().collect::<Vec<_>>().join(" ");
+		self.text.clear();
+
+		match tag {
+			// Top level entry
+			"entry" => {
+				let entry = self.cur_entry.take().unwrap_or_default();
+				if entry.kanji.len() + entry.reading.len() == 0 {
+					return Err(self.error("empty entry"));
+				} else if entry.sense.is_empty() {
+					return Err(self.error("entry has no sense information"));
+				} else if entry.sequence.is_empty() {
+					return Err(self.error("entry has no sequence number"));
+				}
+				entries.push(entry);
+			}
+			"ent_seq" => self.entry()?.sequence = text,
+
+			// Kanji element
+			"k_ele" => {
+				let kanji = self.cur_kanji.take().unwrap_or_default();
+				if kanji.expr.is_empty() {
+					return Err(self.error("kanji element is empty"));
+				}
+				self.entry()?.kanji.push(kanji);
+			}
+			"keb" => self.kanji()?.expr = text,
+			"ke_inf" => {
+				let tag = self.check_tag(text, tags)?;
+				self.kanji()?.info.extend(tag);
+			}
+			"ke_pri" => {
+				let tag = self.check_priority(text)?;
+				self.kanji()?.priority.push(tag);
+			}
+
+			// Reading element
+			"r_ele" => {
+				let reading = self.cur_reading.take().unwrap_or_default();
+				if reading.expr.is_empty() {
+					return Err(self.error("reading element is empty"));
+				}
+				self.entry()?.reading.push(reading);
+			}
+			"reb" => self.reading()?.expr = text,
+			"re_restr" => {
+				let text = self.check_text(text)?;
+				self.reading()?.restrict.extend(text);
+			}
+			"re_inf" => {
+				let tag = self.check_tag(text, tags)?;
+				self.reading()?.info.extend(tag);
+			}
+			"re_pri" => {
+				let tag = self.check_priority(text)?;
+				self.reading()?.priority.push(tag);
+			}
+
+			// Sense element
+			"sense" => {
+				let sense = self.cur_sense.take().unwrap_or_default();
+				if sense.glossary.is_empty() {
+					return Err(self.error("sense element is empty"));
+				}
+				self.entry()?.sense.push(sense);
+			}
+			"lsource" => {
+				let mut source = self.cur_source.take().unwrap_or_default();
+				source.text = text;
+				if source.lang.is_empty() {
+					return Err(self.error("sense language source has no language information"));
+				}
+				self.sense()?.source.push(source);
+			}
+			"gloss" => {
+				let mut glossary = self.cur_glossary.take().unwrap_or_default();
+				glossary.text = text;
+				if glossary.text.is_empty() {
+					return Err(self.error("sense glossary is empty"));
+				}
+				self.sense()?.glossary.push(glossary);
+			}
+			"pos" | "field" | "dial" => {
+				let tag_name = self.check_tag(text, tags)?;
+				let sense = self.sense()?;
+				let list = match tag {
+					"pos" => &mut sense.pos,
+					"field" => &mut sense.field,
+					_ => &mut sense.dialect,
+				};
+				list.extend(tag_name);
+			}
+			"stagk" | "stagr" | "xref" | "ant" | "misc" | "s_inf" => {
+				let text = self.check_text(text)?;
+				let sense = self.sense()?;
+				let list = match tag {
+					"stagk" => &mut sense.stag_kanji,
+					"stagr" => &mut sense.stag_reading,
+					"xref" => &mut sense.xref,
+					"ant" => &mut sense.antonym,
+					"misc" => &mut sense.misc,
+					_ => &mut sense.info,
+				};
+				list.extend(text);
+			}
+			_ => {}
+		}
+
+		self.tags.pop();
+		Ok(())
+	}
+
+	/// Validates a free text value. Empty values are ignored.
+	fn check_text(&self, text: String) -> io::Result<Option<String>> {
+		if text.contains('\n') || text.contains("||") {
+			return Err(self.error(format!("text contains invalid characters: {}", text)));
+		}
+		Ok(if text.is_empty() { None } else { Some(text) })
+	}
+
+	/// Validates a tag against the declared entities. The unclassified tag is
+	/// ignored.
+	fn check_tag(&self, tag: String, tags: &HashMap<String, String>) -> io::Result<Option<String>> {
+		if tag == TAG_UNCLASSIFIED {
+			return Ok(None);
+		}
+		if !tags.contains_key(&tag) {
+			return Err(self.error(format!("invalid tag: {}", tag)));
+		}
+		Ok(Some(tag))
+	}
+
+	fn check_priority(&self, tag: String) -> io::Result<String> {
+		let valid = match tag.as_str() {
+			"news1" | "news2" | "ichi1" | "ichi2" | "spec1" | "spec2" | "gai1" | "gai2" => true,
+			_ => {
+				tag.len() == 4
+					&& tag.starts_with("nf")
+					&& tag[2..].chars().all(|c| c.is_ascii_digit())
+			}
+		};
+		if !valid {
+			return Err(self.error(format!("invalid priority tag: {}", tag)));
+		}
+		Ok(tag)
+	}
+
+	fn entry(&mut self) -> io::Result<&mut Entry> {
+		let err = self.error("element outside of entry");
+		self.cur_entry.as_mut().ok_or(err)
+	}
+
+	fn kanji(&mut self) -> io::Result<&mut EntryKanji> {
+		let err = self.error("element outside of k_ele");
+		self.cur_kanji.as_mut().ok_or(err)
+	}
+
+	fn reading(&mut self) -> io::Result<&mut EntryReading> {
+		let err = self.error("element outside of r_ele");
+		self.cur_reading.as_mut().ok_or(err)
+	}
+
+	fn sense(&mut self) -> io::Result<&mut EntrySense> {
+		let err = self.error("element outside of sense");
+		self.cur_sense.as_mut().ok_or(err)
+	}
+
+	fn error<S: AsRef<str>>(&self, message: S) -> io::Error {
+		invalid_data(format!("{} {}", message.as_ref(), self.at_pos()))
+	}
+
+	/// Describes the current position in the file for error messages.
+	fn at_pos(&self) -> String {
+		let tags = self.tags.join(".");
+		let label = self.cur_entry.as_ref().map(|x| x.word()).unwrap_or("");
+		if label.is_empty() {
+			format!("at {}", tags)
+		} else {
+			format!("at {} ({})", label, tags)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const DOCTYPE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE JMdict [
+<!ENTITY n "noun (common) (futsuumeishi)">
+<!ENTITY v1 "Ichidan verb">
+<!ENTITY uk "word usually written using kana alone">
+]>"#;
+
+	fn parse_str(entries: &str) -> io::Result<Import> {
+		parse(format!("{}<JMdict>{}</JMdict>", DOCTYPE, entries).as_bytes())
+	}
+
+	#[test]
+	fn parses_entries() {
+		let import = parse_str(
+			"<entry>
+				<ent_seq>1000</ent_seq>
+				<k_ele><keb>今日</keb><ke_pri>news1</ke_pri><ke_pri>nf01</ke_pri></k_ele>
+				<k_ele><keb>今</keb></k_ele>
+				<r_ele><reb>きょう</reb><re_restr>今日</re_restr></r_ele>
+				<r_ele><reb>こんにち</reb><re_nokanji/></r_ele>
+				<sense>
+					<stagk>今日</stagk>
+					<pos>&n;</pos>
+					<misc>&uk;</misc>
+					<lsource xml:lang=\"ger\" ls_type=\"part\">Heute</lsource>
+					<gloss>today</gloss>
+					<gloss g_type=\"lit\">this day</gloss>
+				</sense>
+			</entry>",
+		)
+		.unwrap();
+
+		assert_eq!(import.tags.len(), 3);
+		assert_eq!(import.tags["v1"], "Ichidan verb");
+		assert_eq!(import.entries.len(), 1);
+
+		let entry = &import.entries[0];
+		assert_eq!(entry.sequence, "1000");
+		assert_eq!(entry.kanji[0].expr, "今日");
+		assert_eq!(entry.kanji[0].priority, vec!["news1", "nf01"]);
+		assert_eq!(entry.reading[0].restrict, vec!["今日"]);
+		assert!(!entry.reading[0].no_kanji);
+		assert!(entry.reading[1].no_kanji);
+
+		// Entities are kept as the tag names.
+		let sense = &entry.sense[0];
+		assert_eq!(sense.pos, vec!["n"]);
+		assert_eq!(sense.misc, vec!["uk"]);
+		assert_eq!(sense.stag_kanji, vec!["今日"]);
+		assert_eq!(sense.source[0].lang, "ger");
+		assert!(sense.source[0].partial);
+		assert_eq!(sense.glossary[1].text, "this day");
+		assert_eq!(sense.glossary[1].kind, Some(GlossaryType::Literal));
+	}
+
+	#[test]
+	fn validates_entries() {
+		let err = |xml: &str| {
+			parse_str(xml)
+				.err()
+				.map(|x| x.to_string())
+				.unwrap_or_default()
+		};
+		let sense = "<sense><gloss>a</gloss></sense>";
+		let reading = "<r_ele><reb>あ</reb></r_ele>";
+
+		let no_sequence = format!("<entry>{}{}</entry>", reading, sense);
+		assert!(err(&no_sequence).starts_with("entry has no sequence number"));
+
+		let no_sense = format!("<entry><ent_seq>1</ent_seq>{}</entry>", reading);
+		assert!(err(&no_sense).starts_with("entry has no sense information"));
+
+		let unknown_tag = format!(
+			"<entry><ent_seq>1</ent_seq>{}<sense><pos>x</pos><gloss>a</gloss></sense></entry>",
+			reading
+		);
+		assert!(err(&unknown_tag).starts_with("invalid tag: x"));
+
+		let priority = format!(
+			"<entry><ent_seq>1</ent_seq><r_ele><reb>あ</reb><re_pri>top</re_pri></r_ele>{}</entry>",
+			sense
+		);
+		assert!(err(&priority).starts_with("invalid priority tag: top"));
+	}
+}
@@ -0,0 +1,325 @@
+/// Kanji character loaded from KANJIDIC2.
+///
+/// This directly correlates to the `<character>` element in the XML source.
+/// See `kanjidic2.md` in the `data/source` directory for details.
+#[derive(Clone, Debug, Default)]
+pub struct Kanji {
+	/// The character itself (`literal`).
+	pub literal: String,
+
+	/// Kanji grade level (`grade`). 1 through 6 are the Kyouiku kanji, 8 the
+	/// remaining Jouyou kanji, 9 and 10 the Jinmeiyou kanji.
+	pub grade: Option<u8>,
+
+	/// Stroke counts for the kanji (`stroke_count`). The first is the accepted
+	/// count, while the others are common miscounts.
+	pub stroke_count: Vec<usize>,
+
+	/// Frequency-of-use ranking from 1 to 2,500 for the most used characters
+	/// (`freq`).
+	pub ranking: Option<usize>,
+
+	/// Frequency per million for the kanji, if available.
+	pub frequency: Option<f64>,
+
+	/// Former JLPT level from 1 (most advanced) to 4 (`jlpt`).
+	pub old_jlpt: Option<u8>,
+
+	/// JLPT level from 1 to 5, if available.
+	pub jlpt: Option<u8>,
+
+	/// Radical classification for the kanji (`rad_value`).
+	pub radicals: Vec<KanjiRadical>,
+
+	/// Names for the kanji when it is itself a radical, in hiragana
+	/// (`rad_name`).
+	pub radical_names: Vec<String>,
+
+	/// Japanese readings now only associated with names (`nanori`).
+	pub nanori: Vec<String>,
+
+	/// Readings and meanings, grouped by meaning (`rmgroup`).
+	pub groups: Vec<KanjiGroup>,
+
+	/// Cross-references to variants or alternative codes (`variant`).
+	pub variants: Vec<KanjiCode>,
+
+	/// Codes used for finding the kanji by its glyph (`q_code`).
+	pub query_codes: Vec<KanjiQueryCode>,
+
+	/// References in published dictionaries and books (`dic_ref`).
+	pub dict: Vec<KanjiDictRef>,
+}
+
+impl Kanji {
+	/// Accepted stroke count for the kanji.
+	pub fn stroke(&self) -> usize {
+		self.stroke_count.first().cloned().unwrap_or_default()
+	}
+
+	/// Minimum stroke count, including common miscounts.
+	pub fn stroke_min(&self) -> usize {
+		self.stroke_count.iter().cloned().min().unwrap_or_default()
+	}
+
+	/// Maximum stroke count, including common miscounts.
+	pub fn stroke_max(&self) -> usize {
+		self.stroke_count.iter().cloned().max().unwrap_or_default()
+	}
+
+	/// All readings of the given type (e.g. `ja_on`, `ja_kun`).
+	pub fn readings(&self, kind: &str) -> Vec<&str> {
+		let readings = self.groups.iter().flat_map(|x| x.readings.iter());
+		readings
+			.filter(|x| x.kind == kind)
+			.map(|x| x.value.as_str())
+			.collect()
+	}
+
+	/// All meanings for the language, as a two-letter ISO 639-1 code.
+	pub fn meanings(&self, lang: &str) -> Vec<&str> {
+		let meanings = self.groups.iter().flat_map(|x| x.meanings.iter());
+		meanings
+			.filter(|x| x.lang == lang)
+			.map(|x| x.text.as_str())
+			.collect()
+	}
+}
+
+/// Filter for searching kanji. Empty fields match any kanji.
+#[derive(Clone, Debug, Default)]
+pub struct KanjiFilter {
+	/// Component radicals the kanji must have, from the KRADFILE
+	/// decomposition.
+	pub radicals: Vec<String>,
+
+	/// Minimum stroke count.
+	pub stroke_min: Option<usize>,
+
+	/// Maximum stroke count.
+	pub stroke_max: Option<usize>,
+
+	/// SKIP code. This also matches misclassification codes.
+	pub skip: Option<String>,
+
+	/// Four corner code.
+	pub four_corner: Option<String>,
+
+	/// De Roo code.
+	pub deroo: Option<String>,
+
+	/// Kanji grade level.
+	pub grade: Option<u8>,
+
+	/// JLPT level.
+	pub jlpt: Option<u8>,
+}
+
+impl KanjiFilter {
+	/// True if the kanji matches all fields of the filter.
+	///
+	/// Stroke counts match if any of the kanji stroke counts is in range, so
+	/// that common miscounts are also accepted.
+	///
+	/// Codes can be partial, matching only the first parts of the kanji code
+	/// (e.g. `1-4` matches the SKIP code `1-4-3`).
+	///
+	/// The `components` are the KRADFILE components of the kanji, used to
+	/// match the radicals.
+	pub fn matches(&self, kanji: &Kanji, components: &[String]) -> bool {
+		let has_radicals = self
+			.radicals
+			.iter()
+			.all(|radical| components.contains(radical));
+		let stroke_min = self.stroke_min.unwrap_or(0);
+		let stroke_max = self.stroke_max.unwrap_or(usize::MAX);
+		let has_stroke = kanji
+			.stroke_count
+			.iter()
+			.any(|&x| x >= stroke_min && x <= stroke_max);
+		let has_code = |kind: &str, code: &Option<String>| match code {
+			Some(code) => kanji
+				.query_codes
+				.iter()
+				.any(|x| x.kind == kind && code_matches(&x.value, code)),
+			None => true,
+		};
+		has_radicals
+			&& has_stroke
+			&& has_code("skip", &self.skip)
+			&& has_code("four_corner", &self.four_corner)
+			&& has_code("deroo", &self.deroo)
+			&& (self.grade.is_none() || kanji.grade == self.grade)
+			&& (self.jlpt.is_none() || kanji.jlpt == self.jlpt)
+	}
+}
+
+/// Matches a query code against a partial code, split at `-` or `.`.
+fn code_matches(value: &str, code: &str) -> bool {
+	match value.strip_prefix(code) {
+		Some(rest) => rest.is_empty() || rest.starts_with('-') || rest.starts_with('.'),
+		None => false,
+	}
+}
+
+/// Radical classification for a [Kanji].
+#[derive(Clone, Debug, Default)]
+pub struct KanjiRadical {
+	/// Radical number, from 1 to 214.
+	pub value: usize,
+
+	/// Classification type: `classical` for the KangXi Zidian system, or
+	/// `nelson_c` where Nelson reclassified the kanji.
+	pub kind: String,
+}
+
+/// Group of readings and meanings for a [Kanji] (`rmgroup`).
+#[derive(Clone, Debug, Default)]
+pub struct KanjiGroup {
+	/// Readings for the kanji in several languages (`reading`).
+	pub readings: Vec<KanjiCode>,
+
+	/// Meanings for the kanji in several languages (`meaning`).
+	pub meanings: Vec<KanjiMeaning>,
+}
+
+/// Meaning of a [Kanji] in a given language.
+#[derive(Clone, Debug, Default)]
+pub struct KanjiMeaning {
+	/// Two-letter ISO 639-1 language code.
+	pub lang: String,
+
+	/// Text for the meaning.
+	pub text: String,
+}
+
+/// Typed value for a [Kanji], used for readings and variants.
+///
+/// Reading types are `pinyin`, `korean_r`, `korean_h`, `vietnam`, `ja_on`,
+/// and `ja_kun`. Kun readings include the okurigana separated by a `.`.
+///
+/// Variant types are `jis208`, `jis212`, `jis213`, `deroo`, `njecd`, `s_h`,
+/// `nelson_c`, `oneill`, and `ucs`.
+#[derive(Clone, Debug, Default)]
+pub struct KanjiCode {
+	/// Type for the value.
+	pub kind: String,
+
+	/// Value, with contents depending on the type.
+	pub value: String,
+}
+
+/// Query code for a [Kanji].
+#[derive(Clone, Debug, Default)]
+pub struct KanjiQueryCode {
+	/// Type for the code: `skip`, `sh_desc`, `four_corner`, or `deroo`.
+	pub kind: String,
+
+	/// Code value, with format depending on the type (e.g. `4-7-1` for SKIP).
+	pub value: String,
+
+	/// For SKIP codes, the type of misclassification if this is not the
+	/// correct code: `posn`, `stroke_count`, `stroke_and_posn`, or
+	/// `stroke_diff`.
+	pub misclass: Option<String>,
+}
+
+/// Dictionary reference for a [Kanji].
+#[derive(Clone, Debug, Default)]
+pub struct KanjiDictRef {
+	/// Name of the dictionary (e.g. `heisig6`, `nelson_c`). See
+	/// [KANJI_DICT_NAMES].
+	pub name: String,
+
+	/// Reference for the kanji in the dictionary (e.g. index or page).
+	pub text: String,
+}
+
+/// Names and descriptions of the dictionaries referenced by [KanjiDictRef].
+pub const KANJI_DICT_NAMES: &[(&str, &str)] = &[
+	("nelson_c", "\"Modern Reader's Japanese-English Character Dictionary\", edited by Andrew Nelson (now published as the \"Classic\" Nelson)"),
+	("nelson_n", "\"The New Nelson Japanese-English Character Dictionary\", edited by John Haig"),
+	("halpern_njecd", "\"New Japanese-English Character Dictionary\", edited by Jack Halpern"),
+	("halpern_kkd", "\"Kodansha Kanji Dictionary\", (2nd Ed. of the NJECD) edited by Jack Halpern"),
+	("halpern_kkld", "\"Kanji Learners Dictionary\" (Kodansha) edited by Jack Halpern"),
+	("halpern_kkld_2ed", "\"Kanji Learners Dictionary\" (Kodansha), 2nd edition (2013) edited by Jack Halpern"),
+	("heisig", "\"Remembering The Kanji\" by James Heisig"),
+	("heisig6", "\"Remembering The Kanji, Sixth Ed.\" by James Heisig"),
+	("gakken", "\"A New Dictionary of Kanji Usage\" (Gakken)"),
+	("oneill_names", "\"Japanese Names\", by P.G. O'Neill"),
+	("oneill_kk", "\"Essential Kanji\" by P.G. O'Neill"),
+	("moro", "\"Daikanwajiten\" compiled by Morohashi"),
+	("henshall", "\"A Guide To Remembering Japanese Characters\" by Kenneth G. Henshall"),
+	("sh_kk", "\"Kanji and Kana\" by Spahn and Hadamitzky"),
+	("sh_kk2", "\"Kanji and Kana\" by Spahn and Hadamitzky (2011 edition)"),
+	("sakade", "\"A Guide To Reading and Writing Japanese\" edited by Florence Sakade"),
+	("jf_cards", "Japanese Kanji Flashcards, by Max Hodges and Tomoko Okazaki (Series 1)"),
+	("henshall3", "\"A Guide To Reading and Writing Japanese\" 3rd edition, edited by Henshall, Seeley and De Groot"),
+	("tutt_cards", "Tuttle Kanji Cards, compiled by Alexander Kask"),
+	("crowley", "\"The Kanji Way to Japanese Language Power\" by Dale Crowley"),
+	("kanji_in_context", "\"Kanji in Context\" by Nishiguchi and Kono"),
+	("busy_people", "\"Japanese For Busy People\" vols I-III, published by the AJLT"),
+	("kodansha_compact", "\"Kodansha Compact Kanji Guide\"."),
+	("maniette", "Codes from Yves Maniette's \"Les Kanjis dans la tete\" French adaptation of Heisig"),
+];
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn kanji(stroke_count: &[usize], skip: &str) -> Kanji {
+		Kanji {
+			stroke_count: stroke_count.to_vec(),
+			radicals: vec![KanjiRadical {
+				value: 7,
+				kind: "classical".to_string(),
+			}],
+			query_codes: vec![KanjiQueryCode {
+				kind: "skip".to_string(),
+				value: skip.to_string(),
+				misclass: None,
+			}],
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn filters_by_stroke_count() {
+		let filter = KanjiFilter {
+			stroke_min: Some(6),
+			stroke_max: Some(6),
+			..Default::default()
+		};
+		assert!(!filter.matches(&kanji(&[7], "4-7-1"), &[]));
+		assert!(filter.matches(&kanji(&[7, 6], "4-7-1"), &[]));
+	}
+
+	#[test]
+	fn filters_by_partial_code() {
+		let filter = |code: &str| KanjiFilter {
+			skip: Some(code.to_string()),
+			..Default::default()
+		};
+		assert!(filter("4-7-1").matches(&kanji(&[7], "4-7-1"), &[]));
+		assert!(filter("4-7").matches(&kanji(&[7], "4-7-1"), &[]));
+		assert!(!filter("4-7").matches(&kanji(&[7], "4-71-1"), &[]));
+		assert!(!filter("4-7-2").matches(&kanji(&[7], "4-7-1"), &[]));
+	}
+
+	#[test]
+	fn filters_by_radicals() {
+		let filter = |radicals: &[&str]| KanjiFilter {
+			radicals: radicals.iter().map(|x| x.to_string()).collect(),
+			..Default::default()
+		};
+		let components: Vec<String> = ["口", "一", "亅"].iter().map(|x| x.to_string()).collect();
+		let check =
+			|radicals: &[&str]| filter(radicals).matches(&kanji(&[7], "4-7-1"), &components);
+		assert!(check(&[]));
+		assert!(check(&["口"]));
+		assert!(check(&["口", "一"]));
+		assert!(check(&["亅", "一", "口"]));
+		assert!(!check(&["口", "木"]));
+		assert!(!filter(&["口"]).matches(&kanji(&[7], "4-7-1"), &[]));
+	}
+}
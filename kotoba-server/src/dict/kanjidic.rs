@@ -0,0 +1,376 @@
+use std::io;
+use std::path::Path;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::files::{invalid_data, read_zip};
+
+use super::kanji::*;
+
+const RADICAL_TYPES: &[&str] = &["classical", "nelson_c"];
+
+const VARIANT_TYPES: &[&str] = &[
+	"jis208", "jis212", "jis213", "deroo", "njecd", "s_h", "nelson_c", "oneill", "ucs",
+];
+
+const READING_TYPES: &[&str] = &[
+	"pinyin", "korean_r", "korean_h", "vietnam", "ja_on", "ja_kun",
+];
+
+const QUERY_CODE_TYPES: &[&str] = &["skip", "sh_desc", "four_corner", "deroo"];
+
+const SKIP_MISCLASS_TYPES: &[&str] = &["posn", "stroke_count", "stroke_and_posn", "stroke_diff"];
+
+/// Imports the kanji from the `kanjidic2.zip` file, in the same order as the
+/// source file.
+pub fn import_kanji(filename: &Path) -> io::Result<Vec<Kanji>> {
+	let data = read_zip(filename, "kanjidic2.xml")?;
+	parse(&data)
+}
+
+/// Parses the KANJIDIC2 XML data.
+fn parse(data: &[u8]) -> io::Result<Vec<Kanji>> {
+	let mut reader = Reader::from_reader(data);
+	reader.expand_empty_elements(true);
+
+	let mut entries = Vec::new();
+	let mut ctx = Context::default();
+	let mut buf = Vec::new();
+	loop {
+		let event = reader.read_event(&mut buf).map_err(|err| {
+			invalid_data(format!(
+				"parsing XML at {}: {}",
+				reader.buffer_position(),
+				err
+			))
+		})?;
+		match event {
+			Event::Start(ref tag) => {
+				ctx.open_tag(tag)?;
+			}
+			Event::End(ref tag) => {
+				let tag = String::from_utf8_lossy(tag.name()).to_string();
+				ctx.close_tag(&tag, &mut entries)?;
+			}
+			Event::Text(ref text) => {
+				let text = text.unescaped().map_err(|err| {
+					invalid_data(format!("invalid text {}: {}", ctx.at_pos(), err))
+				})?;
+				ctx.text.push_str(&String::from_utf8_lossy(&text));
+			}
+			Event::Eof => break,
+			_ => {}
+		}
+		buf.clear();
+	}
+
+	Ok(entries)
+}
+
+/// Parsing context for the XML elements.
+#[derive(Default)]
+struct Context {
+	tags: Vec<String>,
+	text: String,
+	attrs: Vec<(String, String)>,
+
+	cur_kanji: Option<Kanji>,
+	cur_group: Option<KanjiGroup>,
+}
+
+impl Context {
+	fn open_tag(&mut self, tag: &BytesStart) -> io::Result<()> {
+		let name = String::from_utf8_lossy(tag.name()).to_string();
+		self.text.clear();
+		self.attrs.clear();
+		for attr in tag.attributes() {
+			let attr = attr.map_err(|err| invalid_data(format!("{} {}", err, self.at_pos())))?;
+			let key = String::from_utf8_lossy(attr.key).to_string();
+			let value = String::from_utf8_lossy(&attr.value).to_string();
+			self.attrs.push((key, value));
+		}
+
+		match name.as_str() {
+			"character" => self.cur_kanji = Some(Kanji::default()),
+			"rmgroup" => self.cur_group = Some(KanjiGroup::default()),
+			_ => {}
+		}
+
+		self.tags.push(name);
+		Ok(())
+	}
+
+	fn close_tag(&mut self, tag: &str, entries: &mut Vec<Kanji>) -> io::Result<()> {
+		let text = self.text.split_whitespace().collect::<Vec<_>>().join(" ");
+		self.text.clear();
+
+		match tag {
+			// Top level entry
+			"character" => {
+				let kanji = self.cur_kanji.take().unwrap_or_default();
+				if kanji.literal.is_empty() {
+					return Err(self.error("empty entry"));
+				} else if kanji.radicals.is_empty() {
+					return Err(self.error("kanji has no radicals"));
+				} else if kanji.stroke_count.is_empty() {
+					return Err(self.error("kanji has no stroke information"));
+				}
+				entries.push(kanji);
+			}
+			"literal" => self.kanji()?.literal = text,
+			"rad_value" => {
+				let kind = self.check_type("rad_type", RADICAL_TYPES)?;
+				let value = self.check_number(&text, 1, 214)?;
+				self.kanji()?.radicals.push(KanjiRadical { value, kind });
+			}
+
+			// Values inside `<misc>`
+			"grade" => self.kanji()?.grade = Some(self.check_number(&text, 1, 10)? as u8),
+			"jlpt" => self.kanji()?.old_jlpt = Some(self.check_number(&text, 1, 5)? as u8),
+			"stroke_count" => {
+				let count = self.check_number(&text, 1, usize::MAX)?;
+				self.kanji()?.stroke_count.push(count);
+			}
+			"variant" => {
+				let kind = self.check_type("var_type", VARIANT_TYPES)?;
+				if text.is_empty() {
+					return Err(self.error(format!("variant has no text {}", kind)));
+				}
+				self.kanji()?.variants.push(KanjiCode { kind, value: text });
+			}
+			"freq" => self.kanji()?.ranking = Some(self.check_number(&text, 1, usize::MAX)?),
+			"rad_name" => {
+				if text.is_empty() {
+					return Err(self.error("kanji rad_name is empty"));
+				}
+				self.kanji()?.radical_names.push(text);
+			}
+
+			// Dictionary references
+			"dic_ref" => {
+				let name = self.attr("dr_type").unwrap_or_default().to_string();
+				if !KANJI_DICT_NAMES.iter().any(|x| x.0 == name) {
+					return Err(self.error(format!("dictionary entry name is unknown: {}", name)));
+				}
+				let mut text = text;
+				if text.is_empty() {
+					return Err(self.error(format!("missing dictionary entry text for {}", name)));
+				}
+				if name == "moro" {
+					let vol = self.attr("m_vol").map(|x| format!("volume {}", x));
+					let page = self.attr("m_page").map(|x| format!("page {}", x));
+					let index: Vec<String> = vol.into_iter().chain(page).collect();
+					if !index.is_empty() {
+						text = format!("{} ({})", text, index.join(", "));
+					}
+				}
+				let dict = &mut self.kanji()?.dict;
+				if !dict.iter().any(|x| x.name == name && x.text == text) {
+					dict.push(KanjiDictRef { name, text });
+				}
+			}
+
+			// Query codes
+			"q_code" => {
+				let kind = self.check_type("qc_type", QUERY_CODE_TYPES)?;
+				let misclass = match self.attr("skip_misclass") {
+					Some(_) => Some(self.check_type("skip_misclass", SKIP_MISCLASS_TYPES)?),
+					None => None,
+				};
+				self.kanji()?.query_codes.push(KanjiQueryCode {
+					kind,
+					value: text,
+					misclass,
+				});
+			}
+
+			// Readings & meanings
+			"rmgroup" => {
+				let group = self.cur_group.take().unwrap_or_default();
+				if group.readings.len() + group.meanings.len() == 0 {
+					return Err(self.error("empty reading/meaning group"));
+				}
+				self.kanji()?.groups.push(group);
+			}
+			"reading" => {
+				let kind = self.check_type("r_type", READING_TYPES)?;
+				if text.is_empty() {
+					return Err(self.error(format!("reading for {} is empty", kind)));
+				}
+				self.group()?.readings.push(KanjiCode { kind, value: text });
+			}
+			"meaning" => {
+				let lang = self.attr("m_lang").unwrap_or("en").to_string();
+				if lang.len() != 2 || !lang.chars().all(|x| x.is_ascii_lowercase()) {
+					return Err(
+						self.error(format!("invalid language '{}' for meaning {}", lang, text))
+					);
+				}
+				if text.is_empty() {
+					return Err(self.error(format!("meaning for {} is empty", lang)));
+				}
+				self.group()?.meanings.push(KanjiMeaning { lang, text });
+			}
+			"nanori" => {
+				if text.is_empty() {
+					return Err(self.error("nanori reading is empty"));
+				}
+				self.kanji()?.nanori.push(text);
+			}
+			_ => {}
+		}
+
+		self.tags.pop();
+		Ok(())
+	}
+
+	fn attr(&self, key: &str) -> Option<&str> {
+		self.attrs.iter().find(|x| x.0 == key).map(|x| x.1.as_str())
+	}
+
+	/// Validates a type attribute against the list of valid types.
+	fn check_type(&self, key: &str, valid: &[&str]) -> io::Result<String> {
+		let value = self.attr(key).unwrap_or_default();
+		if !valid.contains(&value) {
+			return Err(self.error(format!("invalid {}: {}", key, value)));
+		}
+		Ok(value.to_string())
+	}
+
+	fn check_number(&self, text: &str, min: usize, max: usize) -> io::Result<usize> {
+		match text.parse::<usize>() {
+			Ok(value) if value >= min && value <= max => Ok(value),
+			_ => Err(self.error(format!("invalid number: {}", text))),
+		}
+	}
+
+	fn kanji(&mut self) -> io::Result<&mut Kanji> {
+		let err = self.error("element outside of character");
+		self.cur_kanji.as_mut().ok_or(err)
+	}
+
+	fn group(&mut self) -> io::Result<&mut KanjiGroup> {
+		let err = self.error("element outside of rmgroup");
+		self.cur_group.as_mut().ok_or(err)
+	}
+
+	fn error<S: AsRef<str>>(&self, message: S) -> io::Error {
+		invalid_data(format!("{} {}", message.as_ref(), self.at_pos()))
+	}
+
+	/// Describes the current position in the file for error messages.
+	fn at_pos(&self) -> String {
+		let tags = self.tags.join(".");
+		let label = self
+			.cur_kanji
+			.as_ref()
+			.map(|x| x.literal.as_str())
+			.unwrap_or("");
+		if label.is_empty() {
+			format!("at {}", tags)
+		} else {
+			format!("at {} ({})", label, tags)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse_str(characters: &str) -> io::Result<Vec<Kanji>> {
+		let xml = format!(
+			"<?xml version=\"1.0\" encoding=\"UTF-8\"?><kanjidic2>{}</kanjidic2>",
+			characters
+		);
+		parse(xml.as_bytes())
+	}
+
+	#[test]
+	fn parses_characters() {
+		let list = parse_str(
+			"<character>
+				<literal>亜</literal>
+				<radical><rad_value rad_type=\"classical\">7</rad_value></radical>
+				<misc><grade>8</grade><stroke_count>7</stroke_count><jlpt>1</jlpt></misc>
+				<dic_number>
+					<dic_ref dr_type=\"nelson_c\">43</dic_ref>
+					<dic_ref dr_type=\"moro\" m_vol=\"1\" m_page=\"0525\">272</dic_ref>
+				</dic_number>
+				<query_code>
+					<q_code qc_type=\"skip\">4-7-1</q_code>
+					<q_code qc_type=\"skip\" skip_misclass=\"posn\">1-1-6</q_code>
+				</query_code>
+				<reading_meaning>
+					<rmgroup>
+						<reading r_type=\"ja_on\">ア</reading>
+						<reading r_type=\"ja_kun\">つ.ぐ</reading>
+						<meaning>Asia</meaning>
+						<meaning m_lang=\"fr\">Asie</meaning>
+					</rmgroup>
+					<nanori>や</nanori>
+				</reading_meaning>
+			</character>",
+		)
+		.unwrap();
+		assert_eq!(list.len(), 1);
+
+		let kanji = &list[0];
+		assert_eq!(kanji.literal, "亜");
+		assert_eq!(kanji.radicals[0].value, 7);
+		assert_eq!(kanji.grade, Some(8));
+		assert_eq!(kanji.stroke_count, vec![7]);
+		assert_eq!(kanji.old_jlpt, Some(1));
+		assert_eq!(kanji.nanori, vec!["や"]);
+
+		assert_eq!(kanji.dict.len(), 2);
+		assert_eq!(kanji.dict[1].name, "moro");
+		assert_eq!(kanji.dict[1].text, "272 (volume 1, page 0525)");
+
+		let codes = &kanji.query_codes;
+		assert_eq!(
+			(codes[0].value.as_str(), codes[0].misclass.clone()),
+			("4-7-1", None)
+		);
+		assert_eq!(codes[1].value, "1-1-6");
+		assert_eq!(codes[1].misclass.as_deref(), Some("posn"));
+
+		assert_eq!(kanji.groups.len(), 1);
+		let group = &kanji.groups[0];
+		assert_eq!(group.readings[1].kind, "ja_kun");
+		assert_eq!(group.readings[1].value, "つ.ぐ");
+		assert_eq!(group.meanings[0].lang, "en");
+		assert_eq!(group.meanings[1].lang, "fr");
+		assert_eq!(group.meanings[1].text, "Asie");
+	}
+
+	#[test]
+	fn validates_characters() {
+		let err = |xml: &str| {
+			parse_str(xml)
+				.err()
+				.map(|x| x.to_string())
+				.unwrap_or_default()
+		};
+		let radical = "<radical><rad_value rad_type=\"classical\">7</rad_value></radical>";
+		let misc = "<misc><stroke_count>7</stroke_count></misc>";
+
+		let no_radical = format!("<character><literal>亜</literal>{}</character>", misc);
+		assert!(err(&no_radical).starts_with("kanji has no radicals"));
+
+		let misclass = format!(
+			"<character><literal>亜</literal>{}{}<query_code>\
+				<q_code qc_type=\"skip\" skip_misclass=\"x\">1-1-6</q_code>\
+			</query_code></character>",
+			radical, misc
+		);
+		assert!(err(&misclass).starts_with("invalid skip_misclass: x"));
+
+		let empty_group = format!(
+			"<character><literal>亜</literal>{}{}<reading_meaning><rmgroup/></reading_meaning></character>",
+			radical, misc
+		);
+		assert!(err(&empty_group).starts_with("empty reading/meaning group"));
+	}
+}
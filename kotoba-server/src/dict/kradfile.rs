@@ -0,0 +1,71 @@
+use std::io;
+use std::path::Path;
+
+use encoding_rs::EUC_JP;
+
+use crate::files::{invalid_data, read_zip};
+
+/// Files inside the `kradzip.zip` archive with the kanji decomposition. The
+/// second file covers the JIS X 0212 kanji.
+const KRADFILE_NAMES: &[&str] = &["kradfile", "kradfile2"];
+
+/// Imports the kanji decomposition from the `kradzip.zip` file. Returns each
+/// kanji with its list of components.
+pub fn import_components(filename: &Path) -> io::Result<Vec<(String, Vec<String>)>> {
+	let mut output = Vec::new();
+	for name in KRADFILE_NAMES {
+		let data = read_zip(filename, name)?;
+		let (text, _, has_errors) = EUC_JP.decode(&data);
+		if has_errors {
+			return Err(invalid_data(format!("{} has invalid EUC-JP data", name)));
+		}
+		let list = parse(&text).map_err(|err| invalid_data(format!("{}: {}", name, err)))?;
+		output.extend(list);
+	}
+	Ok(output)
+}
+
+/// Parses the KRADFILE format, where each line is in the form `亜 : ｜ 一 口`
+/// and comments start with `#`.
+fn parse(text: &str) -> Result<Vec<(String, Vec<String>)>, String> {
+	let mut output = Vec::new();
+	for (index, line) in text.lines().enumerate() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+		let mut parts = line.splitn(2, " : ");
+		let kanji = parts.next().unwrap_or_default().trim();
+		let components = parts.next().unwrap_or_default();
+		let components: Vec<String> = components
+			.split_whitespace()
+			.map(|x| x.to_string())
+			.collect();
+		if kanji.chars().count() != 1 || components.is_empty() {
+			return Err(format!("invalid line {}: {}", index + 1, line));
+		}
+		output.push((kanji.to_string(), components));
+	}
+	Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_kradfile() {
+		let text = "# comment\n亜 : ｜ 一 口\n\n唖 : ｜ 一 口\n";
+		let list = parse(text).unwrap();
+		assert_eq!(list.len(), 2);
+		assert_eq!(list[0].0, "亜");
+		assert_eq!(list[0].1, vec!["｜", "一", "口"]);
+		assert_eq!(list[1].0, "唖");
+	}
+
+	#[test]
+	fn fails_on_invalid_lines() {
+		assert!(parse("亜 :").is_err());
+		assert!(parse("亜唖 : 一").is_err());
+	}
+}
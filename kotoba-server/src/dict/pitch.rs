@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::files::invalid_data;
+
+use super::entry::{Entry, EntryPitch};
+
+/// Pitch accents by the word and reading pair. For kana-only words, the
+/// reading is empty.
+pub type PitchMap = HashMap<(String, String), Vec<EntryPitch>>;
+
+/// Maps the tags in the source file to their JMdict names.
+const TAGS: &[(&str, &str)] = &[
+	("副", "adv"),
+	("名", "n"),
+	("代", "pn"),
+	("形動", "adj-na"),
+	("感", "int"),
+];
+
+/// Imports the pitch accents from the `accents.txt` file.
+pub fn import_pitch(filename: &Path) -> io::Result<PitchMap> {
+	let text = fs::read_to_string(filename)?;
+	let mut output = PitchMap::new();
+	for (index, line) in text.lines().enumerate() {
+		if line.trim().is_empty() {
+			continue;
+		}
+		let error =
+			|message: String| invalid_data(format!("{} at {}: {}", message, index + 1, line));
+		let fields: Vec<&str> = line.split('\t').collect();
+		let (word, read, list) = match fields[..] {
+			[word, read, list] => (word, read, list),
+			_ => return Err(error(String::from("invalid line"))),
+		};
+		let pitches = list
+			.split(',')
+			.map(|x| parse_pitch(x).map_err(&error))
+			.collect::<io::Result<Vec<_>>>()?;
+		output.insert((word.to_string(), read.to_string()), pitches);
+	}
+	Ok(output)
+}
+
+/// Attaches the pitch accents to the readings of the entries. Returns the
+/// number of pitch accents added.
+///
+/// Pitches are looked up by the kanji elements the reading applies to, or by
+/// the reading alone for kana-only entries.
+pub fn merge_pitch(entries: &mut [Entry], pitch: &PitchMap) -> usize {
+	let mut count = 0;
+	for entry in entries.iter_mut() {
+		let kanji: Vec<String> = entry.kanji.iter().map(|x| x.expr.clone()).collect();
+		for reading in entry.reading.iter_mut() {
+			let keys: Vec<(String, String)> = if !reading.restrict.is_empty() {
+				let restrict = reading.restrict.iter();
+				restrict
+					.map(|x| (x.clone(), reading.expr.clone()))
+					.collect()
+			} else if !kanji.is_empty() {
+				kanji
+					.iter()
+					.map(|x| (x.clone(), reading.expr.clone()))
+					.collect()
+			} else {
+				vec![(reading.expr.clone(), String::new())]
+			};
+
+			let mora = reading.mora();
+			for key in keys {
+				for it in pitch.get(&key).into_iter().flatten() {
+					let exists = reading
+						.pitches
+						.iter()
+						.any(|x| x.value == it.value && x.tags == it.tags);
+					if !exists {
+						reading.pitches.push(EntryPitch { mora, ..it.clone() });
+						count += 1;
+					}
+				}
+			}
+		}
+	}
+	count
+}
+
+/// Parses a single pitch value, with optional tags (e.g. `(副;名)0`).
+fn parse_pitch(text: &str) -> Result<EntryPitch, String> {
+	let (tags, value) = match text.strip_prefix('(') {
+		Some(text) => {
+			let mut parts = text.splitn(2, ')');
+			let tags = parts.next().unwrap_or_default();
+			(tags, parts.next().unwrap_or_default())
+		}
+		None => ("", text),
+	};
+	let tags = tags
+		.split(';')
+		.filter(|x| !x.is_empty())
+		.map(|tag| match TAGS.iter().find(|x| x.0 == tag) {
+			Some((_, name)) => Ok(name.to_string()),
+			None => Err(format!("invalid tag: {}", tag)),
+		})
+		.collect::<Result<Vec<_>, _>>()?;
+	let value = value
+		.parse::<usize>()
+		.map_err(|_| format!("invalid pitch: {}", text))?;
+	Ok(EntryPitch {
+		value,
+		tags,
+		mora: 0,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_pitch() {
+		let pitch = parse_pitch("2").unwrap();
+		assert_eq!(pitch.value, 2);
+		assert!(pitch.tags.is_empty());
+
+		let pitch = parse_pitch("(副;名)0").unwrap();
+		assert_eq!(pitch.value, 0);
+		assert_eq!(pitch.tags, vec!["adv", "n"]);
+
+		assert!(parse_pitch("(x)0").is_err());
+		assert!(parse_pitch("a").is_err());
+	}
+}
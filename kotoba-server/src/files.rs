@@ -0,0 +1,43 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Reads the entire contents of a single file inside a zip archive.
+pub fn read_zip(filename: &Path, name: &str) -> io::Result<Vec<u8>> {
+	let file = File::open(filename)?;
+	let mut zip = zip::ZipArchive::new(file)?;
+	let mut entry = zip.by_name(name)?;
+	let mut data = Vec::with_capacity(entry.size() as usize);
+	entry.read_to_end(&mut data)?;
+	Ok(data)
+}
+
+/// Returns an [io::Error] for invalid data in a source file.
+pub fn invalid_data<S: AsRef<str>>(message: S) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message.as_ref())
+}
+
+/// Reads all files inside a zip archive for which the filter returns true,
+/// sorted by name.
+pub fn read_zip_all<F: Fn(&str) -> bool>(
+	filename: &Path,
+	filter: F,
+) -> io::Result<Vec<(String, Vec<u8>)>> {
+	let file = File::open(filename)?;
+	let mut zip = zip::ZipArchive::new(file)?;
+	let mut names: Vec<String> = zip
+		.file_names()
+		.filter(|x| filter(x))
+		.map(String::from)
+		.collect();
+	names.sort();
+
+	let mut output = Vec::with_capacity(names.len());
+	for name in names {
+		let mut entry = zip.by_name(&name)?;
+		let mut data = Vec::with_capacity(entry.size() as usize);
+		entry.read_to_end(&mut data)?;
+		output.push((name, data));
+	}
+	Ok(output)
+}
@@ -1,4 +1,21 @@
+use std::convert::TryFrom;
+
+use juniper::FieldResult;
+
 use crate::app::App;
+use crate::dict;
+
+mod entry;
+mod history;
+mod kanji;
+mod result;
+mod segment;
+
+pub use entry::*;
+pub use history::*;
+pub use kanji::*;
+pub use result::*;
+pub use segment::*;
 
 pub struct Context {
 	pub app: &'static App,
@@ -9,22 +26,348 @@ impl juniper::Context for Context {}
 /// Root Query for the GraphQL schema.
 pub struct Query;
 
-#[graphql_object(Context = Context)]
+#[graphql_object(Context = Context, rename = "none")]
 impl Query {
 	/// Server version.
 	fn app() -> &'static str {
 		"Kotoba Server"
 	}
+
+	/// Total number of words in the dictionary.
+	fn word_count(context: &Context) -> i32 {
+		context.app.dict().entries().len() as i32
+	}
+
+	/// Returns words by position in the default sort order (by popularity).
+	#[graphql(arguments(offset(default = 0), limit(default = 100)))]
+	fn words(context: &Context, offset: i32, limit: i32) -> Vec<Entry> {
+		let offset = offset.max(0) as usize;
+		let limit = limit.max(0) as usize;
+		let entries = context.app.dict().entries();
+		entries
+			.iter()
+			.skip(offset)
+			.take(limit)
+			.map(Entry::new)
+			.collect()
+	}
+
+	/// Returns words for the JLPT level (1 to 5) sorted by frequency. Words
+	/// without frequency information come last, by position.
+	///
+	/// Note that the JLPT levels are not official.
+	#[graphql(arguments(offset(default = 0), limit(default = 100)))]
+	fn words_by_jlpt(context: &Context, level: i32, offset: i32, limit: i32) -> Vec<Entry> {
+		let level = match u8::try_from(level) {
+			Ok(level) => level,
+			Err(_) => return Vec::new(),
+		};
+		let offset = offset.max(0) as usize;
+		let limit = limit.max(0) as usize;
+		let entries = context.app.dict().words_by_jlpt(level);
+		entries
+			.into_iter()
+			.skip(offset)
+			.take(limit)
+			.map(Entry::new)
+			.collect()
+	}
+
+	/// Entries saved to the word history, most recent first.
+	fn history(context: &Context) -> Vec<HistoryEntry> {
+		let list = context.app.history().list();
+		list.into_iter()
+			.map(|(id, time)| HistoryEntry::new(id, time))
+			.collect()
+	}
+
+	/// Retrieves a dictionary entry by its id.
+	fn entry(context: &Context, id: String) -> Option<Entry> {
+		context.app.dict().get(&id).map(Entry::new)
+	}
+
+	/// Retrieves a list of dictionary entries by their id.
+	fn entries(context: &Context, ids: Vec<String>) -> Vec<Entry> {
+		let entries = context.app.dict().get_list(&ids);
+		entries.into_iter().map(Entry::new).collect()
+	}
+
+	/// Looks up the dictionary form of a conjugated word.
+	///
+	/// The input is converted to hiragana and de-inflected using all the
+	/// applicable inflection rules. Only entries which have the grammar tags
+	/// expected by the rules are returned, sorted by their position.
+	///
+	/// The match information for each entry contains the inflected suffix
+	/// and the rules that were applied.
+	fn deinflect(context: &Context, input: String) -> Vec<Entry> {
+		let matches = context.app.deinflect(&input);
+		matches
+			.iter()
+			.map(|m| Entry::with_match(m.entry, EntryMatch::from_deinflect(m)))
+			.collect()
+	}
+
+	/// Looks up all words in a phrase, including conjugated words.
+	///
+	/// The phrase is de-inflected at every position, favoring the longest
+	/// matches. Each entry match contains the position in the input where the
+	/// word was found. Words at the end of the input may match a partially
+	/// typed inflection, in which case the inflected suffix is split by a
+	/// dot (.) at the completed part.
+	///
+	/// Entries are returned in the order they appear in the input.
+	fn deinflect_all(context: &Context, input: String) -> Vec<Entry> {
+		let matches = context.app.deinflect_all(&input);
+		matches
+			.iter()
+			.map(|m| Entry::with_match(m.entry, EntryMatch::from_deinflect(m)))
+			.collect()
+	}
+
+	/// Splits a text into words, including conjugated words.
+	///
+	/// The segmentation covers as much of the text as possible, favoring
+	/// fewer, longer, and more frequent words. Each segment also lists the
+	/// alternative words at its position.
+	fn segment(context: &Context, text: String) -> Vec<Segment> {
+		context
+			.app
+			.segment(&text)
+			.into_iter()
+			.map(Segment::from)
+			.collect()
+	}
+
+	/// Looks up the words starting at an offset of the text, including
+	/// conjugated words. This is meant for hover lookups, without having to
+	/// segment the whole text.
+	///
+	/// The offset is in characters, and words are matched up to `max_length`
+	/// characters. The result includes the range of the text that matched.
+	#[graphql(arguments(max_length(default = 20)))]
+	fn scan(context: &Context, text: String, offset: i32, max_length: i32) -> Scan {
+		let offset = offset.max(0) as usize;
+		let max_length = (max_length.max(0) as usize).min(crate::segment::MAX_WORD_LENGTH);
+		context.app.scan(&text, offset, max_length).into()
+	}
+
+	/// Looks up a kanji by its character.
+	fn kanji(context: &Context, character: String) -> Option<Kanji> {
+		context.app.dict().get_kanji(&character).map(Kanji::new)
+	}
+
+	/// Looks up all kanji in the given string, in the order they appear.
+	///
+	/// Characters that are not in the kanji dictionary are skipped, as are
+	/// repeated characters.
+	fn kanji_list(context: &Context, characters: String) -> Vec<Kanji> {
+		let dict = context.app.dict();
+		let mut list: Vec<char> = Vec::new();
+		for chr in characters.chars() {
+			if !list.contains(&chr) {
+				list.push(chr);
+			}
+		}
+		list.into_iter()
+			.filter_map(|chr| dict.get_kanji(&chr.to_string()))
+			.map(Kanji::new)
+			.collect()
+	}
+
+	/// Searches kanji by their components and properties, sorted by
+	/// frequency.
+	///
+	/// All given filters must match:
+	/// - 'radicals' are component radicals from the KRADFILE decomposition,
+	///   as listed by the kanji 'components'. The kanji must have all of them.
+	/// - 'stroke_min' and 'stroke_max' give a stroke count range. Kanji where
+	///   a common miscount is in range are also included.
+	/// - 'skip', 'four_corner' and 'deroo' are query codes. Those can be
+	///   partial (e.g. "1-4" matches the SKIP code "1-4-3"). SKIP codes also
+	///   match known misclassifications.
+	/// - 'grade' and 'jlpt' match the exact level.
+	#[graphql(arguments(offset(default = 0), limit(default = 100)))]
+	fn kanji_search(
+		context: &Context,
+		radicals: Option<Vec<String>>,
+		stroke_min: Option<i32>,
+		stroke_max: Option<i32>,
+		skip: Option<String>,
+		four_corner: Option<String>,
+		deroo: Option<String>,
+		grade: Option<i32>,
+		jlpt: Option<i32>,
+		offset: i32,
+		limit: i32,
+	) -> Vec<Kanji> {
+		// Levels out of range can't match any kanji.
+		let level = |x: Option<i32>| x.map(u8::try_from).transpose();
+		let (grade, jlpt) = match (level(grade), level(jlpt)) {
+			(Ok(grade), Ok(jlpt)) => (grade, jlpt),
+			_ => return Vec::new(),
+		};
+		let filter = dict::KanjiFilter {
+			radicals: radicals.unwrap_or_default(),
+			stroke_min: stroke_min.map(|x| x.max(0) as usize),
+			stroke_max: stroke_max.map(|x| x.max(0) as usize),
+			skip,
+			four_corner,
+			deroo,
+			grade,
+			jlpt,
+		};
+		let offset = offset.max(0) as usize;
+		let limit = limit.max(0) as usize;
+		let list = context.app.dict().search_kanji(&filter);
+		list.into_iter()
+			.skip(offset)
+			.take(limit)
+			.map(Kanji::new)
+			.collect()
+	}
+
+	/// Looks up kanji by their components, as given by the KRADFILE radical
+	/// decomposition.
+	///
+	/// This is meant for a multi-radical lookup, where the components are
+	/// selected one at a time. The result has the kanji with all selected
+	/// components and the components still valid for the next selection.
+	fn kanji_by_components(context: &Context, components: Vec<String>) -> KanjiComponentSearch {
+		let (kanji, valid) = context.app.dict().search_components(&components);
+		KanjiComponentSearch {
+			kanji: kanji.into_iter().map(Kanji::new).collect(),
+			components: valid.into_iter().map(|x| x.to_string()).collect(),
+		}
+	}
+
+	/// Lookup entries by the kanji/reading pair.
+	///
+	/// This searches for an exact match on both the kanji and reading. That
+	/// means no de-inflection, kana conversion, fuzzy matching, prefix, or
+	/// suffix match.
+	///
+	/// The purpose of this field is to lookup for known dictionary entries
+	/// without having to resort to their ID. As such, this lookup is very
+	/// strict in an attempt to match an entry exactly.
+	///
+	/// When kanji is empty, this will only match kana-only entries. If the
+	/// kanji is the same as the reading, it is handled as an empty kanji. This
+	/// is for convenience to allow using an Entry word/read pair for the lookup.
+	///
+	/// Finally, if the kanji/reading pairs are ambiguous towards more than one
+	/// entry, then the match will consider the entries that have that kanji
+	/// and reading as the first entries.
+	///
+	/// If the entry is still ambiguous, all matching entries are returned in
+	/// database order. The database order has the more frequent/popular entries
+	/// first.
+	fn lookup(context: &Context, kanji: String, reading: String) -> Vec<Entry> {
+		let entries = context.app.dict().lookup(&kanji, &reading);
+		entries.into_iter().map(Entry::new).collect()
+	}
+
+	/// Entry search using a query string with support for multiple predicates
+	/// and advanced search operators.
+	///
+	/// Keywords on the search query are matched against both kanji and reading
+	/// elements of dictionary entries.
+	///
+	/// # Keyword Matching and Order
+	///
+	/// When matching a keyword to an entry's text, besides matching the entire
+	/// text, a keyword may also match a prefix, suffix, or be contained within
+	/// the text.
+	///
+	/// The text matching can also be exact, approximate or fuzzy:
+	///
+	/// - Exact compares the text exactly.
+	///
+	/// - Approximate will filter the text to eliminate some common ambiguities
+	/// and typos and then compare. Only letters and digits are considered when
+	/// matching.
+	///
+	/// - Fuzzy is like approximate but will match the characters in any point
+	/// in the text as long as they are in the correct order.
+	///
+	/// By default, any keyword will match exactly and approximately. Fuzzy
+	/// matching will only be used for keywords where it is explicitly enabled.
+	/// It is also possible to only match keywords exactly, i.e. disabling the
+	/// approximate matching.
+	///
+	/// Results are returned in order of relevance:
+	///
+	/// - First results are sorted into exact, approximate, and fuzzy matching
+	/// groups.
+	/// - In each group, results are sorted into full, prefix, suffix, and
+	/// contains matches, in that order.
+	/// - For each of the above result groups, results are sorted first by
+	/// length, then by word popularity, and then frequency.
+	///
+	/// # Query Syntax
+	///
+	/// Note that all operators bellow also accept the fullwidth Japanese
+	/// characters.
+	///
+	/// The query can contain multiple predicates separated by spaces. Entries
+	/// can match either predicate, i.e. they combine with an OR operator.
+	///
+	/// Besides a plain keyword, the following operators can be used in a
+	/// keyword to modify it:
+	///
+	/// - The '=' prefix in a keyword marks it as an exact match.
+	/// - The '>' prefix in a keyword enables fuzzy matching for that keyword.
+	/// - Keywords can also contain the '*' and '?' glob operators to match
+	/// respectively a sequence of zero or more characters, and a single
+	/// character. Those operators are matched independently of the prefix,
+	/// suffix, and contains matches.
+	///
+	/// Note that the keyword operators cannot be split from the keywords by
+	/// spaces.
+	///
+	/// Keywords can be combined with '&' (AND) and '~' (AND NOT) operators.
+	/// Entries will be matched using the combined keywords with any of their
+	/// kanji and reading elements.
+	///
+	/// A full predicate can be negated using the '!' prefix operator. When
+	/// combined with the OR, negated predicates take precedence, meaning that
+	/// any negated that matches will negate the entire OR match.
+	///
+	/// Finally, predicates can be combined using parenthesis and square
+	/// brackets.
+	///
+	/// A standalone ',' (or '、') keyword matches all words saved to the
+	/// history. It can be combined with other keywords as any predicate.
+	#[graphql(arguments(
+		id(
+			description = "User-defined ID for this search. This is returned as-is and has no defined meaning."
+		),
+		query(
+			description = "Search query expression. See the 'search' field documentation for details on the syntax."
+		),
+	))]
+	fn search(context: &Context, id: Option<String>, query: String) -> FieldResult<SearchResult> {
+		let id = id.unwrap_or_else(|| query.clone());
+		let search = context.app.search_for(&query)?;
+		Ok(SearchResult::new(id, search))
+	}
 }
 
 /// Root Mutation for the GraphQL schema.
 pub struct Mutation;
 
-#[graphql_object(Context = Context)]
+#[graphql_object(Context = Context, rename = "none")]
 impl Mutation {
-	/// Dummy operation.
-	fn no_op() -> i32 {
-		42
+	/// Save an entry to the word history. Returns false if the entry does not
+	/// exist.
+	fn insert_history(context: &Context, id: String) -> FieldResult<bool> {
+		Ok(context.app.insert_history(&id)?)
+	}
+
+	/// Remove an entry from the word history. Returns false if the entry was
+	/// not saved.
+	fn remove_history(context: &Context, id: String) -> FieldResult<bool> {
+		Ok(context.app.remove_history(&id)?)
 	}
 }
 
@@ -0,0 +1,611 @@
+use crate::dict;
+use crate::inflection;
+use crate::search;
+
+use super::{Context, Kanji};
+
+/// Tag applicable to dictionary entries.
+#[derive(GraphQLObject)]
+#[graphql(rename = "none")]
+pub struct Tag {
+	/// Tag name.
+	pub name: String,
+
+	/// Description for the tag.
+	pub text: String,
+}
+
+impl Tag {
+	fn list(context: &Context, names: &[String]) -> Vec<Tag> {
+		let dict = context.app.dict();
+		names
+			.iter()
+			.map(|name| Tag {
+				name: name.clone(),
+				text: dict.tag_text(name),
+			})
+			.collect()
+	}
+}
+
+/// This is the root element for a dictionary entry.
+pub struct Entry {
+	data: &'static dict::Entry,
+	match_info: Option<EntryMatch>,
+}
+
+impl Entry {
+	pub fn new(data: &'static dict::Entry) -> Entry {
+		Entry {
+			data,
+			match_info: None,
+		}
+	}
+
+	/// Returns the entry with the given match information.
+	pub fn with_match(data: &'static dict::Entry, match_info: EntryMatch) -> Entry {
+		Entry {
+			data,
+			match_info: Some(match_info),
+		}
+	}
+
+	pub fn data(&self) -> &'static dict::Entry {
+		self.data
+	}
+}
+
+#[graphql_object(Context = Context, rename = "none")]
+impl Entry {
+	/// Unique numeric sequence ID for this entry.
+	fn id(&self) -> &str {
+		&self.data.sequence
+	}
+
+	/// When the entry is loaded through a search, this contains additional
+	/// information about the match.
+	#[graphql(name = "match")]
+	fn match_info(&self) -> &Option<EntryMatch> {
+		&self.match_info
+	}
+
+	/// This will be the expression for the first entry in 'kanji' if available,
+	/// or the first entry in 'reading' otherwise.
+	fn word(&self) -> &str {
+		self.data.word()
+	}
+
+	/// The first reading for the entry.
+	fn read(&self) -> &str {
+		self.data.read()
+	}
+
+	/// Glossary from all senses joined together.
+	fn text(&self) -> String {
+		self.data.text()
+	}
+
+	/// For entries with frequency information, this provides the relative rank
+	/// of the entry across all entries in the dictionary.
+	fn rank(&self) -> Option<i32> {
+		self.data.rank.map(|x| x as i32)
+	}
+
+	/// When available, provides a relative frequency count for the entry across
+	/// multiple source corpus.
+	///
+	/// For any particular entry, this is the maximum frequency of the kanji
+	/// elements. If an entry is kana-only, then this is the maximum frequency
+	/// of the reading elements.
+	fn frequency(&self) -> Option<f64> {
+		self.data.frequency
+	}
+
+	/// Provides the relative position of the entry across all dictionary
+	/// entries.
+	///
+	/// This is similar to rank, but also takes into account popular entries.
+	/// For entries without frequency information, this will take into account
+	/// the relative position of the entries in the source dictionary (which
+	/// also serves as a weaker indication of popularity).
+	fn position(&self) -> i32 {
+		self.data.position as i32
+	}
+
+	/// JLPT level for the entry from 1-5.
+	///
+	/// Note that those are not official and some entries may have been
+	/// misplaced.
+	///
+	/// Some words have two levels (one for reading and the other for the kanji).
+	/// In those cases highest level is provided.
+	fn jlpt(&self) -> Option<i32> {
+		self.data.jlpt.map(|x| x as i32)
+	}
+
+	/// Indicates if this is a popular entry. This is true if any kanji or
+	/// reading element is popular.
+	fn popular(&self) -> bool {
+		self.data.popular()
+	}
+
+	/// List of non-kana readings for the entry. For the most part this will
+	/// be the kanji form of the word, but in some cases this may include
+	/// characters in other scripts.
+	fn kanji(&self) -> Vec<EntryKanji> {
+		self.data.kanji.iter().map(EntryKanji).collect()
+	}
+
+	/// The reading element typically contains the valid readings of the word(s)
+	/// in the kanji element using modern kanadzukai.
+	fn reading(&self) -> Vec<EntryReading> {
+		self.data.reading.iter().map(EntryReading).collect()
+	}
+
+	/// The sense element will record the translational equivalent of the
+	/// word, plus other related information. Where there are several different
+	/// meanings of the word, multiple sense elements will be employed.
+	fn sense(&self) -> Vec<EntrySense> {
+		self.data.sense.iter().map(EntrySense).collect()
+	}
+
+	/// True if this word has been saved to the history.
+	fn saved(&self, context: &Context) -> bool {
+		context.app.history().contains(&self.data.sequence)
+	}
+
+	/// Conjugation table for verbs and adjectives (e.g. polite, negative,
+	/// past, te-form, potential). Empty for entries that cannot be conjugated.
+	///
+	/// Nouns that are also suru-verbs are conjugated with `する`.
+	fn inflections(&self, context: &Context) -> Vec<EntryInflection> {
+		let list = context.app.conjugate(self.data);
+		list.into_iter().map(EntryInflection::from).collect()
+	}
+
+	/// Breakdown of each kanji in the entry word, with the kanji reading used
+	/// in the word.
+	///
+	/// The readings are aligned with the entry reading, considering rendaku
+	/// and small-tsu gemination. Irregular readings (e.g. 今日) are not
+	/// aligned and have no reading.
+	fn kanji_breakdown(&self, context: &Context) -> Vec<EntryKanjiBreakdown> {
+		let list = context.app.dict().kanji_breakdown(self.data);
+		list.into_iter().map(EntryKanjiBreakdown::from).collect()
+	}
+}
+
+/// Kanji from the word of an Entry, with the reading used in the word.
+#[derive(GraphQLObject)]
+#[graphql(rename = "none", context = Context)]
+pub struct EntryKanjiBreakdown {
+	/// Character in the entry word.
+	pub character: String,
+
+	/// Kanji data for the character, if available.
+	pub kanji: Option<Kanji>,
+
+	/// Part of the entry reading (in hiragana) used by this character. Not
+	/// available if the reading is irregular.
+	pub reading: Option<String>,
+
+	/// Kanji reading used in the word, as listed in the kanji readings
+	/// (e.g. "ガク" or "まな.ぶ").
+	pub source: Option<String>,
+
+	/// Type of the kanji reading: "on", "kun", or "nanori".
+	#[graphql(name = "type")]
+	pub kind: Option<String>,
+
+	/// The reading is voiced by rendaku (e.g. か to が).
+	pub rendaku: bool,
+
+	/// The reading ends with a small っ by gemination (e.g. がく to がっ).
+	pub gemination: bool,
+}
+
+impl From<dict::KanjiBreakdown<'static>> for EntryKanjiBreakdown {
+	fn from(it: dict::KanjiBreakdown<'static>) -> EntryKanjiBreakdown {
+		EntryKanjiBreakdown {
+			character: it.character,
+			kanji: it.kanji.map(Kanji::new),
+			reading: it.reading,
+			source: it.source,
+			kind: it.kind,
+			rendaku: it.rendaku,
+			gemination: it.gemination,
+		}
+	}
+}
+
+/// Conjugated form for an Entry.
+#[derive(GraphQLObject)]
+#[graphql(rename = "none")]
+pub struct EntryInflection {
+	/// Name of the conjugation (e.g. "polite", "negative past", "-te").
+	pub name: String,
+
+	/// Inflection rules applied to the dictionary form, in order. These are
+	/// the same rules used by 'inflection_rules' in de-inflected matches.
+	pub rules: Vec<String>,
+
+	/// Conjugated form of the entry 'word'.
+	pub word: String,
+
+	/// Conjugated form of the entry 'read'.
+	pub read: String,
+}
+
+impl From<inflection::Inflection> for EntryInflection {
+	fn from(it: inflection::Inflection) -> EntryInflection {
+		EntryInflection {
+			name: it.name,
+			rules: it.rules,
+			word: it.word,
+			read: it.read,
+		}
+	}
+}
+
+/// For an Entry matched through a search this includes additional
+/// information about the match.
+#[derive(Clone, Debug, Default, GraphQLObject)]
+#[graphql(rename = "none")]
+pub struct EntryMatch {
+	/// Lookup mode that matched the entry.
+	///
+	/// Valid values are:
+	/// - exact, prefix, suffix, contains
+	/// - approx, approx-prefix, approx-suffix, approx-contains
+	/// - fuzzy, fuzzy-prefix, fuzzy-suffix, fuzzy-contains
+	/// - deinflect
+	pub mode: String,
+
+	/// Portion of the search query that matched.
+	pub query: String,
+
+	/// Position from the query that matched. This is only available for
+	/// de-inflected matches.
+	pub position: Option<i32>,
+
+	/// Full text of the kanji or reading that was matched, converted to
+	/// hiragana.
+	pub text: String,
+
+	/// Sequence (possibly non-continuous) of "match_text" that was matched.
+	pub segments: String,
+
+	/// For de-inflected entries, this is the inflected suffix of the
+	/// original query term.
+	///
+	/// Note that if the suffix had to be completed, this will contain
+	/// a dot (.) splitting the completed suffix.
+	pub inflected_suffix: Option<String>,
+
+	/// De-inflection rules used to match this entry.
+	pub inflection_rules: Option<Vec<String>>,
+}
+
+impl EntryMatch {
+	/// Match information for a search result.
+	pub fn from_search(m: &search::Match) -> EntryMatch {
+		EntryMatch {
+			mode: m.mode(),
+			query: m.query.clone(),
+			text: m.text.clone(),
+			segments: m.segments.clone(),
+			..Default::default()
+		}
+	}
+
+	/// Match information for a de-inflected entry. Entries matched without
+	/// applying any rule are reported as `exact`.
+	pub fn from_deinflect(m: &inflection::Match) -> EntryMatch {
+		EntryMatch {
+			mode: if m.rules.is_empty() {
+				"exact"
+			} else {
+				"deinflect"
+			}
+			.to_string(),
+			query: m.query.clone(),
+			position: Some(m.position as i32),
+			text: m.text.clone(),
+			segments: m.prefix.clone(),
+			inflected_suffix: Some(m.suffix.clone()),
+			inflection_rules: Some(m.rules.clone()),
+		}
+	}
+}
+
+/// Represents a kanji element for an Entry.
+pub struct EntryKanji(&'static dict::EntryKanji);
+
+#[graphql_object(Context = Context, rename = "none")]
+impl EntryKanji {
+	/// This element will contain a word or short phrase in Japanese
+	/// which is written using at least one non-kana character (usually kanji,
+	/// but can be other characters, including other alphabets in exceptional
+	/// cases).
+	fn expr(&self) -> &str {
+		&self.0.expr
+	}
+
+	/// List of tags related specifically to the orthography of 'expr', and will
+	/// typically indicate some unusual aspect, such as okurigana irregularity.
+	fn info(&self, context: &Context) -> Vec<Tag> {
+		Tag::list(context, &self.0.info)
+	}
+
+	/// Indicates if this is a popular entry.
+	///
+	/// This is based on the presence of the following priority tags: news1,
+	/// ichi1, spec1, spec2, and gai1.
+	fn popular(&self) -> bool {
+		self.0.popular()
+	}
+
+	/// Labels related to information about the relative priority of the entry,
+	/// and consist of codes indicating the word appears in various references
+	/// which can be taken as an indication of the frequency with which the word
+	/// is used.
+	fn priority(&self, context: &Context) -> Vec<Tag> {
+		Tag::list(context, &self.0.priority)
+	}
+}
+
+/// Represents a reading element for an Entry.
+pub struct EntryReading(&'static dict::EntryReading);
+
+#[graphql_object(Context = Context, rename = "none")]
+impl EntryReading {
+	/// Reading restricted to kana and related characters such as chouon and
+	/// kurikaeshi. Kana usage will be consistent between the kanji and reading
+	/// elements (e.g. if one contains katakana, so will the other).
+	fn expr(&self) -> &str {
+		&self.0.expr
+	}
+
+	/// Indicates that the reading, while associated with the kanji, cannot be
+	/// regarded as a true reading of the kanji. It is typically used for words
+	/// such as foreign place names, gairaigo which can be in kanji or katakana,
+	/// etc.
+	fn no_kanji(&self) -> bool {
+		self.0.no_kanji
+	}
+
+	/// This element is used to indicate when the reading only applies to a
+	/// subset of the kanji elements in the entry. In its absence, all readings
+	/// apply to all kanji elements. The contents of this element exactly match
+	/// those of one of the kanji elements.
+	fn restrict(&self) -> &Vec<String> {
+		&self.0.restrict
+	}
+
+	/// Tags pertaining to the specific reading. Typically it will be used to
+	/// indicate some unusual aspect of the reading.
+	fn info(&self, context: &Context) -> Vec<Tag> {
+		Tag::list(context, &self.0.info)
+	}
+
+	/// Indicates if this is a popular entry.
+	///
+	/// This is based on the presence of the following priority tags: news1,
+	/// ichi1, spec1, spec2, and gai1.
+	fn popular(&self) -> bool {
+		self.0.popular()
+	}
+
+	/// Labels related to information about the relative priority of the entry.
+	///
+	/// See the equivalent field in EntryKanji for more information.
+	fn priority(&self, context: &Context) -> Vec<Tag> {
+		Tag::list(context, &self.0.priority)
+	}
+
+	/// Number of mora in the reading. Small kana (e.g. `ょ`) are part of the
+	/// previous mora, while `ん`, `っ` and `ー` count as their own.
+	fn mora(&self) -> i32 {
+		self.0.mora() as i32
+	}
+
+	/// List of pitch information for the reading.
+	fn pitches(&self) -> Vec<EntryPitch> {
+		self.0.pitches.iter().map(EntryPitch).collect()
+	}
+}
+
+/// Pitch information for an EntryReading.
+pub struct EntryPitch(&'static dict::EntryPitch);
+
+#[graphql_object(Context = Context, rename = "none")]
+impl EntryPitch {
+	/// Pitch information for the reading. This is based on the mora for the
+	/// reading. The values are:
+	/// - 0: First mora is low, all other are high.
+	/// - 1: First mora is high, all other are low.
+	/// - N: First mora is low, then high up to but not including N.
+	fn value(&self) -> i32 {
+		self.0.value as i32
+	}
+
+	/// Number of mora in the reading the pitch applies to.
+	fn mora(&self) -> i32 {
+		self.0.mora as i32
+	}
+
+	/// Accent class for the pitch:
+	/// - 'heiban': flat, with no drop in pitch (value 0).
+	/// - 'atamadaka': the pitch drops after the first mora (value 1).
+	/// - 'nakadaka': the pitch drops inside the word.
+	/// - 'odaka': the pitch drops after the last mora, on a following particle.
+	fn accent(&self) -> &str {
+		self.0.accent()
+	}
+
+	/// Tags for this particular pitch. Existing tags are:
+	/// - 'adv' adverb
+	/// - 'n' noun
+	/// - 'pn' pronoun
+	/// - 'adj-na' adjectival nouns or quasi-adjectives
+	/// - 'int' interjection
+	fn tags(&self, context: &Context) -> Vec<Tag> {
+		Tag::list(context, &self.0.tags)
+	}
+}
+
+/// Sense element for an Entry.
+pub struct EntrySense(&'static dict::EntrySense);
+
+#[graphql_object(Context = Context, rename = "none")]
+impl EntrySense {
+	/// If present, indicate that the sense is restricted to the lexeme
+	/// represented by the respective kanji element.
+	fn stag_kanji(&self) -> &Vec<String> {
+		&self.0.stag_kanji
+	}
+
+	/// If present, indicate that the sense is restricted to the lexeme
+	/// represented by the respective reading element.
+	fn stag_reading(&self) -> &Vec<String> {
+		&self.0.stag_reading
+	}
+
+	/// Tags corresponding to part-of-speech information about the entry/sense.
+	///
+	/// In general where there are multiple senses in an entry, the part-of-speech
+	/// of an earlier sense will apply to later senses unless there is a new
+	/// part-of-speech indicated.
+	fn pos(&self, context: &Context) -> Vec<Tag> {
+		Tag::list(context, &self.0.pos)
+	}
+
+	/// This element is used to indicate a cross-reference to another entry with
+	/// a similar or related meaning or sense. The content of this element is
+	/// typically a kanji or reading element in another entry. In some cases the
+	/// kanji will be followed by reading and/or sense number to provide a precise
+	/// target for the cross-reference. Where this happens, a JIS "centre-dot"
+	/// (0x2126) is placed between the components of the cross-reference.
+	fn xref(&self) -> &Vec<String> {
+		&self.0.xref
+	}
+
+	/// This element is used to indicate another entry which is an antonym of
+	/// the current entry/sense. The content of this element must exactly match
+	/// that of a kanji or reading element in another entry.
+	fn antonym(&self) -> &Vec<String> {
+		&self.0.antonym
+	}
+
+	/// Tags with information about the field of application of the entry/sense.
+	/// When absent, general application is implied.
+	fn field(&self, context: &Context) -> Vec<Tag> {
+		Tag::list(context, &self.0.field)
+	}
+
+	/// Tags used for other relevant information about the entry/sense. As with
+	/// part-of-speech, information will usually apply to several senses.
+	fn misc(&self, context: &Context) -> Vec<Tag> {
+		Tag::list(context, &self.0.misc)
+	}
+
+	/// The sense-information elements provided for additional information to be
+	/// recorded about a sense. Typical usage would be to indicate such things
+	/// as level of currency of a sense, the regional variations, etc.
+	fn info(&self) -> &Vec<String> {
+		&self.0.info
+	}
+
+	/// For words specifically associated with regional dialects in Japanese,
+	/// will contain tags for that dialect (e.g. ksb for Kansaiben).
+	fn dialect(&self, context: &Context) -> Vec<Tag> {
+		Tag::list(context, &self.0.dialect)
+	}
+
+	/// This element records the information about the source language(s) of a
+	/// loan-word/gairaigo. The element value is the source word or phrase.
+	fn source(&self) -> Vec<EntrySenseSource> {
+		self.0.source.iter().map(EntrySenseSource).collect()
+	}
+
+	/// Within each sense will be one or more glossary entries, i.e. words or
+	/// phrases which are equivalents to the Japanese word. This element would
+	/// normally be present, however it may be omitted in entries which are
+	/// purely for a cross-reference.
+	fn glossary(&self) -> Vec<EntrySenseGlossary> {
+		self.0.glossary.iter().map(EntrySenseGlossary).collect()
+	}
+}
+
+/// Source element for an EntrySense.
+pub struct EntrySenseSource(&'static dict::EntrySenseSource);
+
+#[graphql_object(Context = Context, rename = "none")]
+impl EntrySenseSource {
+	/// Text for the entry. This is the word or phrase in the source language.
+	fn text(&self) -> &str {
+		&self.0.text
+	}
+
+	/// The language from which a loanword is drawn. It will be coded using the
+	/// three-letter language code from the ISO 639-2 standard.
+	fn lang(&self) -> &str {
+		&self.0.lang
+	}
+
+	/// Indicates whether the source element fully or partially describes the
+	/// source word or phrase of the loanword.
+	fn partial(&self) -> bool {
+		self.0.partial
+	}
+
+	/// Indicates that the Japanese word has been constructed from words in the
+	/// source language, and not from an actual phrase in that language. Most
+	/// commonly used to indicate "waseieigo".
+	fn wasei(&self) -> bool {
+		self.0.wasei
+	}
+}
+
+/// Glossary element for an EntrySense.
+pub struct EntrySenseGlossary(&'static dict::EntrySenseGlossary);
+
+#[graphql_object(Context = Context, rename = "none")]
+impl EntrySenseGlossary {
+	/// Text for the glossary entry.
+	fn text(&self) -> &str {
+		&self.0.text
+	}
+
+	/// Specifies that the glossary is of a particular type.
+	///
+	/// Possible values are 'literal' | 'figurative' | 'explanation'
+	#[graphql(name = "type")]
+	fn kind(&self) -> Option<&str> {
+		self.0.kind.map(|x| x.as_str())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn deinflect_match(entry: &dict::Entry, rules: &[&str]) -> EntryMatch {
+		EntryMatch::from_deinflect(&inflection::Match {
+			entry,
+			query: String::from("たべた"),
+			position: 0,
+			text: String::from("たべる"),
+			prefix: String::from("たべ"),
+			suffix: String::from("た"),
+			rules: rules.iter().map(|x| x.to_string()).collect(),
+		})
+	}
+
+	#[test]
+	fn reports_deinflect_mode_only_with_rules() {
+		let entry = dict::Entry::default();
+		assert_eq!(deinflect_match(&entry, &[]).mode, "exact");
+		assert_eq!(deinflect_match(&entry, &["past"]).mode, "deinflect");
+	}
+}
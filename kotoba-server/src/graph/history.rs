@@ -0,0 +1,31 @@
+use super::{Context, Entry};
+
+/// An entry saved to the word history.
+pub struct HistoryEntry {
+	id: String,
+	time: u64,
+}
+
+impl HistoryEntry {
+	pub fn new(id: String, time: u64) -> HistoryEntry {
+		HistoryEntry { id, time }
+	}
+}
+
+#[graphql_object(Context = Context, rename = "none")]
+impl HistoryEntry {
+	/// Id for the saved entry.
+	fn id(&self) -> &str {
+		&self.id
+	}
+
+	/// Time the entry was saved, in milliseconds since the Unix epoch.
+	fn time(&self) -> f64 {
+		self.time as f64
+	}
+
+	/// The saved dictionary entry.
+	fn entry(&self, context: &Context) -> Option<Entry> {
+		context.app.dict().get(&self.id).map(Entry::new)
+	}
+}
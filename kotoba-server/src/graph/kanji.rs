@@ -0,0 +1,271 @@
+use crate::dict;
+
+use super::Context;
+
+/// Kanji character from the KANJIDIC2 dictionary.
+pub struct Kanji(&'static dict::Kanji);
+
+impl Kanji {
+	pub fn new(data: &'static dict::Kanji) -> Kanji {
+		Kanji(data)
+	}
+}
+
+#[graphql_object(Context = Context, rename = "none")]
+impl Kanji {
+	/// The kanji character itself.
+	fn character(&self) -> &str {
+		&self.0.literal
+	}
+
+	/// The kanji grade level.
+	///
+	/// - 1 through 6 indicates a Kyouiku kanji and the grade in which the
+	///   kanji is taught in Japanese schools.
+	/// - 8 indicates it is one of the remaining Jouyou Kanji to be learned in
+	///   junior high school.
+	/// - 9 indicates it is a Jinmeiyou (for use in names) kanji.
+	/// - 10 indicates a Jinmeiyou kanji which is a variant of a Jouyou kanji.
+	fn grade(&self) -> Option<i32> {
+		self.0.grade.map(|x| x as i32)
+	}
+
+	/// The accepted stroke count for the kanji, including the radical.
+	fn stroke(&self) -> i32 {
+		self.0.stroke() as i32
+	}
+
+	/// Minimum stroke count, considering common miscounts.
+	fn stroke_min(&self) -> i32 {
+		self.0.stroke_min() as i32
+	}
+
+	/// Maximum stroke count, considering common miscounts.
+	fn stroke_max(&self) -> i32 {
+		self.0.stroke_max() as i32
+	}
+
+	/// All stroke counts for the kanji. The first is the accepted count, while
+	/// subsequent ones are common miscounts.
+	fn stroke_count(&self) -> Vec<i32> {
+		self.0.stroke_count.iter().map(|&x| x as i32).collect()
+	}
+
+	/// Frequency per million for this kanji, when available.
+	fn frequency(&self) -> Option<f64> {
+		self.0.frequency
+	}
+
+	/// A frequency-of-use ranking from 1 to 2500 for the most used kanji. The
+	/// discrimination between the less frequently used kanji is not strong.
+	fn ranking(&self) -> Option<i32> {
+		self.0.ranking.map(|x| x as i32)
+	}
+
+	/// The former JLPT level for the kanji, from 1 (most advanced) to 4 (most
+	/// elementary).
+	fn old_jlpt(&self) -> Option<i32> {
+		self.0.old_jlpt.map(|x| x as i32)
+	}
+
+	/// JLPT level for the kanji from 1 to 5.
+	///
+	/// Note that those are not official.
+	fn jlpt(&self) -> Option<i32> {
+		self.0.jlpt.map(|x| x as i32)
+	}
+
+	/// Radical classification for the kanji.
+	fn radicals(&self) -> Vec<KanjiRadical> {
+		self.0.radicals.iter().map(KanjiRadical::from).collect()
+	}
+
+	/// When the kanji is itself a radical and has a name, this contains the
+	/// name in hiragana.
+	fn radical_names(&self) -> &Vec<String> {
+		&self.0.radical_names
+	}
+
+	/// Japanese readings that are now only associated with names.
+	fn nanori(&self) -> &Vec<String> {
+		&self.0.nanori
+	}
+
+	/// The "on" Japanese readings of the kanji, in katakana.
+	fn on(&self) -> Vec<&str> {
+		self.0.readings("ja_on")
+	}
+
+	/// The "kun" Japanese readings of the kanji, in hiragana. Where relevant
+	/// the okurigana is separated by a ".". Readings associated with prefixes
+	/// and suffixes are marked with a "-".
+	fn kun(&self) -> Vec<&str> {
+		self.0.readings("ja_kun")
+	}
+
+	/// All readings for the kanji in several languages.
+	fn readings(&self) -> Vec<KanjiCode> {
+		let readings = self.0.groups.iter().flat_map(|x| x.readings.iter());
+		readings.map(KanjiCode::from).collect()
+	}
+
+	/// Meanings for the kanji in the language, given as a two-letter ISO 639-1
+	/// code. Defaults to English.
+	#[graphql(arguments(lang(default = "en".to_string())))]
+	fn meanings(&self, lang: String) -> Vec<&str> {
+		self.0.meanings(&lang)
+	}
+
+	/// Either a cross-reference code to another kanji, usually regarded as a
+	/// variant, or an alternative indexing code for the current kanji.
+	///
+	/// Types are: jis208, jis212, jis213, deroo, njecd, s_h, nelson_c, oneill,
+	/// and ucs.
+	fn variants(&self) -> Vec<KanjiCode> {
+		self.0.variants.iter().map(KanjiCode::from).collect()
+	}
+
+	/// Codes with information relating to the glyph, which can be used for
+	/// finding the kanji.
+	fn query_codes(&self) -> Vec<KanjiQueryCode> {
+		self.0
+			.query_codes
+			.iter()
+			.map(KanjiQueryCode::from)
+			.collect()
+	}
+
+	/// Components of the kanji from the KRADFILE radical decomposition.
+	fn components(&self, context: &Context) -> Vec<&str> {
+		let list = context.app.dict().kanji_components(&self.0.literal);
+		list.iter().map(|x| x.as_str()).collect()
+	}
+
+	/// Kanji that have this kanji as a component in the KRADFILE radical
+	/// decomposition, sorted by frequency.
+	fn used_in(&self, context: &Context) -> Vec<Kanji> {
+		let list = context.app.dict().kanji_used_in(&self.0.literal);
+		list.into_iter().map(Kanji::new).collect()
+	}
+
+	/// Index numbers and similar information such as page numbers in a number
+	/// of published dictionaries and instructional books on kanji.
+	fn dict(&self) -> Vec<KanjiDictRef> {
+		self.0.dict.iter().map(KanjiDictRef::from).collect()
+	}
+}
+
+/// Radical classification for a Kanji.
+#[derive(GraphQLObject)]
+#[graphql(rename = "none")]
+pub struct KanjiRadical {
+	/// The radical number, in the range 1 to 214.
+	pub value: i32,
+
+	/// Classification type. Either "classical", based on the system first
+	/// used in the KangXi Zidian, or "nelson_c" where the Nelson dictionary
+	/// reclassified the kanji.
+	#[graphql(name = "type")]
+	pub kind: String,
+}
+
+impl From<&dict::KanjiRadical> for KanjiRadical {
+	fn from(it: &dict::KanjiRadical) -> KanjiRadical {
+		KanjiRadical {
+			value: it.value as i32,
+			kind: it.kind.clone(),
+		}
+	}
+}
+
+/// Typed value for a Kanji reading or variant.
+#[derive(GraphQLObject)]
+#[graphql(rename = "none")]
+pub struct KanjiCode {
+	/// Type for the value.
+	///
+	/// For readings this is one of: pinyin, korean_r, korean_h, vietnam,
+	/// ja_on, ja_kun.
+	#[graphql(name = "type")]
+	pub kind: String,
+
+	/// Value, with contents depending on the type.
+	pub value: String,
+}
+
+impl From<&dict::KanjiCode> for KanjiCode {
+	fn from(it: &dict::KanjiCode) -> KanjiCode {
+		KanjiCode {
+			kind: it.kind.clone(),
+			value: it.value.clone(),
+		}
+	}
+}
+
+/// Query code for a Kanji.
+#[derive(GraphQLObject)]
+#[graphql(rename = "none")]
+pub struct KanjiQueryCode {
+	/// Type for the code:
+	/// - skip: Halpern's SKIP code, in the format `n-nn-nn`.
+	/// - sh_desc: descriptor code for The Kanji Dictionary by Spahn and
+	///   Hadamitzky.
+	/// - four_corner: the "Four Corner" code.
+	/// - deroo: code from De Roo's "2001 Kanji".
+	#[graphql(name = "type")]
+	pub kind: String,
+
+	/// Value for the code.
+	pub value: String,
+
+	/// For SKIP codes, indicates a misclassification: posn, stroke_count,
+	/// stroke_and_posn, or stroke_diff.
+	pub misclass: Option<String>,
+}
+
+impl From<&dict::KanjiQueryCode> for KanjiQueryCode {
+	fn from(it: &dict::KanjiQueryCode) -> KanjiQueryCode {
+		KanjiQueryCode {
+			kind: it.kind.clone(),
+			value: it.value.clone(),
+			misclass: it.misclass.clone(),
+		}
+	}
+}
+
+/// Dictionary reference for a Kanji.
+#[derive(GraphQLObject)]
+#[graphql(rename = "none")]
+pub struct KanjiDictRef {
+	/// Name of the dictionary (e.g. "heisig6").
+	pub name: String,
+
+	/// Description of the dictionary.
+	pub label: String,
+
+	/// Reference for the kanji, such as an index or page number.
+	pub text: String,
+}
+
+impl From<&dict::KanjiDictRef> for KanjiDictRef {
+	fn from(it: &dict::KanjiDictRef) -> KanjiDictRef {
+		let label = dict::KANJI_DICT_NAMES.iter().find(|x| x.0 == it.name);
+		KanjiDictRef {
+			name: it.name.clone(),
+			label: label.map(|x| x.1).unwrap_or_default().to_string(),
+			text: it.text.clone(),
+		}
+	}
+}
+
+/// Result of a kanji lookup by components.
+#[derive(GraphQLObject)]
+#[graphql(rename = "none", context = Context)]
+pub struct KanjiComponentSearch {
+	/// Kanji having all the selected components, sorted by frequency.
+	pub kanji: Vec<Kanji>,
+
+	/// Components that can still be selected to narrow down the result,
+	/// sorted by stroke count.
+	pub components: Vec<String>,
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use crate::search;
+
+use super::{Context, Entry, EntryMatch};
+
+/// Main element for a search result.
+pub struct SearchResult {
+	id: String,
+	search: Arc<search::Search>,
+}
+
+impl SearchResult {
+	pub fn new(id: String, search: Arc<search::Search>) -> SearchResult {
+		SearchResult { id, search }
+	}
+}
+
+#[graphql_object(Context = Context, rename = "none")]
+impl SearchResult {
+	/// The user-supplied id exactly. If no ID was passed, this defaults to
+	/// query.
+	fn id(&self) -> &str {
+		&self.id
+	}
+
+	/// Total number of entries matched across all pages. If 'loading' is true
+	/// this is a partial count of the number of rows loaded so far.
+	fn total(&self) -> i32 {
+		self.search.total() as i32
+	}
+
+	/// Elapsed time in seconds for the entire search. If 'loading' is true,
+	/// this is the partial time elapsed so far.
+	fn elapsed(&self) -> f64 {
+		self.search.elapsed().as_secs_f64()
+	}
+
+	/// This is true if the search is still loading on the backend.
+	///
+	/// A loading search may not return all possible entries for a given page
+	/// range. The 'total' and 'elapsed' fields are also partial running values.
+	fn loading(&self) -> bool {
+		self.search.loading()
+	}
+
+	/// Loads a page from the results.
+	///
+	/// The page must specify a offset in the results (starting from zero) and
+	/// a limit number of entries.
+	///
+	/// Note that unless a search is completed ('loading' is false), it is not
+	/// guaranteed that a page will return all possible entries. The specified
+	/// limit is only a maximum bound on the number of items. The reason for
+	/// this is that pages return as soon as rows are available on their range,
+	/// even if the entire range hasn't been fullfiled and the search is still
+	/// loading.
+	#[graphql(arguments(
+		offset(
+			default = 0,
+			description = "Offset of the first entry in the page. Zero will return the first entry.",
+		),
+		limit(
+			default = 100,
+			description = "Maximum number of entries in the page. Must be a non-zero positive number. See notes on loading and limits for the 'page' field.",
+		),
+	))]
+	async fn page(&self, context: &Context, offset: i32, limit: i32) -> SearchPage {
+		let entries = context.app.dict().entries();
+		let rows = self
+			.search
+			.page(offset.max(0) as usize, limit.max(0) as usize)
+			.await;
+		let entries = rows
+			.iter()
+			.map(|m| Entry::with_match(&entries[m.entry], EntryMatch::from_search(m)))
+			.collect();
+		SearchPage {
+			offset,
+			limit,
+			entries,
+		}
+	}
+}
+
+/// Page inside a SearchResult.
+#[derive(GraphQLObject)]
+#[graphql(Context = Context, rename = "none")]
+pub struct SearchPage {
+	/// The user-specified offset for this page.
+	pub offset: i32,
+
+	/// The user-specified limit for this page.
+	pub limit: i32,
+
+	/// Entries for this page. The first entry, if available, will always have
+	/// the specified offset.
+	///
+	/// The number of entries is limited to the specified limit, but not
+	/// guaranteed to be the full available range. See details on the 'page'
+	/// field for 'SearchResult'.
+	pub entries: Vec<Entry>,
+}
@@ -0,0 +1,105 @@
+use crate::segment;
+
+use super::{Context, Entry, EntryMatch};
+
+/// Segment of a text split into words.
+pub struct Segment {
+	data: segment::Segment<'static>,
+}
+
+impl From<segment::Segment<'static>> for Segment {
+	fn from(data: segment::Segment<'static>) -> Segment {
+		Segment { data }
+	}
+}
+
+#[graphql_object(Context = Context, rename = "none")]
+impl Segment {
+	/// Position of the segment in the input text, in characters.
+	fn position(&self) -> i32 {
+		self.data.position as i32
+	}
+
+	/// Text for the segment. This is the word chosen by the segmentation or
+	/// a sequence of characters not matching any word.
+	fn text(&self) -> &str {
+		&self.data.text
+	}
+
+	/// All words starting at the segment position. The chosen word is first,
+	/// followed by the alternatives from longest to shortest.
+	///
+	/// Empty if the segment text does not match any word.
+	fn words(&self) -> Vec<SegmentWord> {
+		self.data
+			.words
+			.iter()
+			.map(|x| SegmentWord { data: x.clone() })
+			.collect()
+	}
+}
+
+/// Word starting at a segment position.
+pub struct SegmentWord {
+	data: segment::Word<'static>,
+}
+
+#[graphql_object(Context = Context, rename = "none")]
+impl SegmentWord {
+	/// Text for the word, as it appears in the input.
+	fn text(&self) -> &str {
+		&self.data.text
+	}
+
+	/// Dictionary entries matching the word, including de-inflected matches.
+	fn entries(&self) -> Vec<Entry> {
+		self.data
+			.entries
+			.iter()
+			.map(|m| Entry::with_match(m.entry, EntryMatch::from_deinflect(m)))
+			.collect()
+	}
+}
+
+/// Words matched at an offset of a text.
+pub struct Scan {
+	data: segment::Scan<'static>,
+}
+
+impl From<segment::Scan<'static>> for Scan {
+	fn from(data: segment::Scan<'static>) -> Scan {
+		Scan { data }
+	}
+}
+
+#[graphql_object(Context = Context, rename = "none")]
+impl Scan {
+	/// Start of the matched range in the input text, in characters.
+	fn start(&self) -> i32 {
+		self.data.start as i32
+	}
+
+	/// End of the matched range in the input text, in characters (exclusive).
+	///
+	/// This covers the longest match. Use the match query of each entry for
+	/// the range matched by that entry.
+	fn end(&self) -> i32 {
+		self.data.end as i32
+	}
+
+	/// Text for the matched range. Empty if nothing matched.
+	fn text(&self) -> &str {
+		&self.data.text
+	}
+
+	/// Dictionary entries matching at the offset, including de-inflected
+	/// matches. Longer matches come first, then the ones with fewer
+	/// de-inflection rules, then the most popular.
+	fn entries(&self) -> Vec<Entry> {
+		self.data
+			.entries
+			.iter()
+			.map(|m| Entry::with_match(m.entry, EntryMatch::from_deinflect(m)))
+			.collect()
+	}
+}
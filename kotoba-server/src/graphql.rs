@@ -41,7 +41,7 @@ pub async fn query(
 // spell-checker: disable
 
 fn graphiql_source(title: &str, url: &str) -> String {
-	return format!(
+	format!(
 		r#"
 			<!DOCTYPE html>
 			<html>
@@ -66,10 +66,10 @@ fn graphiql_source(title: &str, url: &str) -> String {
 		url = url,
 		style = STYLE,
 		script = SCRIPT,
-	);
+	)
 }
 
-const STYLE: &'static str = r#"
+const STYLE: &str = r#"
 	<style>
 	body {
 		height: 100%;
@@ -83,7 +83,7 @@ const STYLE: &'static str = r#"
 	</style>
 "#;
 
-const SCRIPT: &'static str = r#"
+const SCRIPT: &str = r#"
 	<script>
 		/**
 		 * This GraphiQL example illustrates how to use some of GraphiQL's props
@@ -0,0 +1,143 @@
+//! Word history, with the entries saved by the user.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::files::invalid_data;
+
+/// Name of the history file in the store directory.
+const HISTORY_FILE: &str = "word_history.json";
+
+/// Saved dictionary entries, persisted as a JSON file mapping each entry id
+/// to the time it was saved.
+#[derive(Default)]
+pub struct History {
+	/// File used to persist the history. This is [None] if the history could
+	/// not be loaded, in which case changes fail instead of overwriting it.
+	path: Option<PathBuf>,
+	entries: Mutex<HashMap<String, u64>>,
+}
+
+impl History {
+	/// Loads the history from the store directory. A missing file is loaded
+	/// as an empty history.
+	pub fn load(store_dir: &Path) -> io::Result<History> {
+		let path = store_dir.join(HISTORY_FILE);
+		let entries = match fs::read_to_string(&path) {
+			Ok(text) => serde_json::from_str(&text)
+				.map_err(|err| invalid_data(format!("parsing {}: {}", path.display(), err)))?,
+			Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+			Err(err) => return Err(err),
+		};
+		Ok(History {
+			path: Some(path),
+			entries: Mutex::new(entries),
+		})
+	}
+
+	/// Saved entry ids with the time they were saved, in milliseconds since
+	/// the Unix epoch. Most recent entries come first.
+	pub fn list(&self) -> Vec<(String, u64)> {
+		let entries = self.entries.lock().unwrap();
+		let mut list: Vec<(String, u64)> = entries
+			.iter()
+			.map(|(id, &time)| (id.clone(), time))
+			.collect();
+		list.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+		list
+	}
+
+	/// True if the entry id is saved.
+	pub fn contains(&self, id: &str) -> bool {
+		self.entries.lock().unwrap().contains_key(id)
+	}
+
+	/// Saves the entry id with the current time. Saving an existing id
+	/// updates its time.
+	pub fn insert(&self, id: &str) -> io::Result<()> {
+		let time = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|x| x.as_millis() as u64)
+			.unwrap_or_default();
+		let mut entries = self.entries.lock().unwrap();
+		let previous = entries.insert(id.to_string(), time);
+		let result = self.save(&entries);
+		if result.is_err() {
+			// Restore the in-memory state to match the saved history.
+			match previous {
+				Some(time) => entries.insert(id.to_string(), time),
+				None => entries.remove(id),
+			};
+		}
+		result
+	}
+
+	/// Removes the entry id. Returns false if the id was not saved.
+	pub fn remove(&self, id: &str) -> io::Result<bool> {
+		let mut entries = self.entries.lock().unwrap();
+		let time = match entries.remove(id) {
+			Some(time) => time,
+			None => return Ok(false),
+		};
+		let result = self.save(&entries);
+		if result.is_err() {
+			entries.insert(id.to_string(), time);
+		}
+		result.map(|_| true)
+	}
+
+	/// Writes the entries to a temporary file and then replaces the history
+	/// file, so it is never left partially written. The temporary file is
+	/// synced before the rename, so the replaced file is durable.
+	fn save(&self, entries: &HashMap<String, u64>) -> io::Result<()> {
+		let path = match &self.path {
+			Some(path) => path,
+			None => return Err(io::Error::other("history is not available")),
+		};
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+		let text = serde_json::to_string_pretty(entries)?;
+		let temp = path.with_extension("json.tmp");
+		let mut file = fs::File::create(&temp)?;
+		file.write_all(text.as_bytes())?;
+		file.sync_all()?;
+		fs::rename(&temp, path)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn persists_history() {
+		let dir = std::env::temp_dir().join(format!("kotoba-history-{}", std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+
+		let history = History::load(&dir).unwrap();
+		assert!(history.list().is_empty());
+		history.insert("1000").unwrap();
+		history.insert("2000").unwrap();
+		assert!(history.contains("1000"));
+		assert!(history.remove("1000").unwrap());
+		assert!(!history.remove("1000").unwrap());
+
+		let history = History::load(&dir).unwrap();
+		let ids: Vec<String> = history.list().into_iter().map(|x| x.0).collect();
+		assert_eq!(ids, vec!["2000"]);
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn fails_to_save_unavailable_history() {
+		let history = History::default();
+		assert!(history.insert("1000").is_err());
+		assert!(!history.contains("1000"));
+	}
+}
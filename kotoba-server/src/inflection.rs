@@ -0,0 +1,704 @@
+//! De-inflection of conjugated Japanese words.
+//!
+//! This is a port of `server-node/dict/inflection.ts`, using the rule table
+//! from `data/grammar/deinflect.json`.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Instant;
+
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::Deserialize;
+
+use crate::dict::{Dict, Entry};
+use crate::files::invalid_data;
+use crate::kana;
+
+mod conjugation;
+
+pub use conjugation::*;
+
+/// Single de-inflection rule, mapping an inflected suffix back to the
+/// dictionary form.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+	/// Name of the inflection (e.g. `-te`, `past`).
+	#[serde(skip)]
+	pub name: String,
+
+	/// Inflected suffix.
+	pub kana_in: String,
+
+	/// Suffix for the de-inflected term.
+	pub kana_out: String,
+
+	/// Grammar tags the inflected term must have for the rule to apply. Empty
+	/// for the rules that apply to a term with no previous rule.
+	pub rules_in: Vec<String>,
+
+	/// Grammar tags for the de-inflected term.
+	pub rules_out: Vec<String>,
+}
+
+/// Table of de-inflection rules, in the source order.
+#[derive(Default)]
+pub struct Rules {
+	rules: Vec<Rule>,
+}
+
+impl Rules {
+	/// Loads the rules from the JSON file. Lines starting with `//` are
+	/// ignored as comments.
+	pub fn load(filename: &Path) -> io::Result<Rules> {
+		let start = Instant::now();
+		let text = std::fs::read_to_string(filename)?;
+		let rules = Rules::parse(&text)?;
+		println!(
+			"inf: loaded {} inflection rules from {} in {:.2?}",
+			rules.rules.len(),
+			filename.display(),
+			start.elapsed()
+		);
+		Ok(rules)
+	}
+
+	/// Parses the rules from the JSON text.
+	pub fn parse(text: &str) -> io::Result<Rules> {
+		let text: Vec<&str> = text
+			.lines()
+			.filter(|x| !x.trim().starts_with("//"))
+			.collect();
+		serde_json::from_str(&text.join("\n"))
+			.map_err(|err| invalid_data(format!("parsing inflection rules: {}", err)))
+	}
+
+	pub fn list(&self) -> &[Rule] {
+		&self.rules
+	}
+}
+
+impl<'de> Deserialize<'de> for Rules {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Rules, D::Error> {
+		// The source is a map of the inflection name to the list of rules. We
+		// flatten it, preserving the source order.
+		struct RulesVisitor;
+
+		impl<'de> Visitor<'de> for RulesVisitor {
+			type Value = Rules;
+
+			fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				f.write_str("a map of inflection names to rules")
+			}
+
+			fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Rules, A::Error> {
+				let mut rules = Vec::new();
+				while let Some((name, list)) = map.next_entry::<String, Vec<Rule>>()? {
+					for mut rule in list {
+						rule.name = name.clone();
+						rules.push(rule);
+					}
+				}
+				Ok(Rules { rules })
+			}
+		}
+
+		deserializer.deserialize_map(RulesVisitor)
+	}
+}
+
+/// De-inflected entry.
+#[derive(Clone, Debug)]
+pub struct Match<'a> {
+	pub entry: &'a Entry,
+
+	/// Original term that was de-inflected.
+	pub query: String,
+
+	/// Position of the term in the query.
+	pub position: usize,
+
+	/// Kanji or reading (in hiragana) that matched the de-inflected term.
+	pub text: String,
+
+	/// Uninflected prefix of the text.
+	pub prefix: String,
+
+	/// Inflected suffix of the original term. If the suffix had to be
+	/// completed, this contains a dot (.) splitting the completed suffix.
+	pub suffix: String,
+
+	/// Names of the rules applied to de-inflect the term, from the inflected
+	/// term to the dictionary form.
+	pub rules: Vec<String>,
+}
+
+/// Looks up the dictionary entries for the de-inflected forms of the word.
+pub fn deinflect<'a>(dict: &'a Dict, rules: &Rules, word: &str) -> Vec<Match<'a>> {
+	let mut deinflector = Deinflector::new(rules);
+	deinflector.add(&kana::to_hiragana(word), false);
+	deinflector.filter(dict)
+}
+
+/// Looks up the dictionary entries for the words in a phrase, trying to
+/// de-inflect the text at every position.
+///
+/// Longer matches take precedence, and each part of the phrase is matched
+/// by at most one segment. Entries are sorted by the position of the match.
+pub fn deinflect_all<'a>(dict: &'a Dict, rules: &Rules, phrase: &str) -> Vec<Match<'a>> {
+	let mut deinflector = Deinflector::new(rules);
+	deinflector.add_phrase(phrase);
+	deinflector.deinflect_all(dict, phrase)
+}
+
+/// Maximum phrase length, in characters, for [Deinflector::add_phrase].
+const MAX_PHRASE_LENGTH: usize = 150;
+
+/// Maximum segment length, in characters, for [Deinflector::add_phrase]. This
+/// limits the segment to a reasonable word size.
+const MAX_SEGMENT_LENGTH: usize = 50;
+
+/// De-inflection candidate.
+#[derive(Clone, Debug)]
+struct Candidate<'a> {
+	/// The term resulting from the de-inflection.
+	term: String,
+
+	/// The original term.
+	source: String,
+
+	/// Position from the query that matched this candidate.
+	position: usize,
+
+	/// If the original de-inflection is partial, this is the added suffix.
+	partial: String,
+
+	/// The uninflected prefix.
+	prefix: String,
+
+	/// Expected grammar rule tags for the de-inflected term.
+	rules: &'a [String],
+
+	/// List of de-inflection rules applied to the source term, starting with
+	/// the last rule applied.
+	reasons: Vec<&'a Rule>,
+}
+
+/// Segment of a phrase added with [Deinflector::add_phrase].
+#[derive(Clone, Debug)]
+struct Segment {
+	text: String,
+
+	/// Start position in the phrase, in characters.
+	start: usize,
+
+	/// End position in the phrase, in characters.
+	end: usize,
+
+	/// Segments at the end of the phrase allow partial de-inflection.
+	partial: bool,
+}
+
+/// Generates de-inflection candidates for a set of terms and filters the
+/// matching dictionary entries to only the valid de-inflections.
+pub struct Deinflector<'a> {
+	rules: &'a Rules,
+
+	/// Maps the resulting de-inflected terms to the list of candidates that
+	/// generated the term.
+	map: HashMap<String, Vec<Rc<Candidate<'a>>>>,
+
+	/// Maps each added term and partial flag to its list of candidates.
+	by_source: HashMap<(String, bool), Vec<Rc<Candidate<'a>>>>,
+
+	/// Segments for each added phrase, in the order they are matched.
+	segments: HashMap<String, Vec<Segment>>,
+}
+
+impl<'a> Deinflector<'a> {
+	pub fn new(rules: &'a Rules) -> Deinflector<'a> {
+		Deinflector {
+			rules,
+			map: HashMap::new(),
+			by_source: HashMap::new(),
+			segments: HashMap::new(),
+		}
+	}
+
+	/// Adds a term to the de-inflection list, generating all the possible
+	/// candidates for it. The term should be converted to hiragana.
+	///
+	/// If `allow_partial` is true, the term may also be de-inflected from a
+	/// partially typed suffix.
+	pub fn add(&mut self, term: &str, allow_partial: bool) {
+		let key = (term.to_string(), allow_partial);
+		if self.by_source.contains_key(&key) {
+			return;
+		}
+
+		let list: Vec<Rc<Candidate>> = self
+			.candidates(term, allow_partial)
+			.into_iter()
+			.map(Rc::new)
+			.collect();
+		for it in list.iter() {
+			self.map
+				.entry(it.term.clone())
+				.or_default()
+				.push(it.clone());
+		}
+		self.by_source.insert(key, list);
+	}
+
+	/// Adds an entire phrase to the de-inflection list. This will add all
+	/// segments of the phrase, to find de-inflected words inside it.
+	///
+	/// Segments at the end of the phrase are added with partial
+	/// de-inflection, to support a partially typed suffix.
+	pub fn add_phrase(&mut self, phrase: &str) {
+		if self.segments.contains_key(phrase) {
+			return;
+		}
+
+		let chars: Vec<char> = phrase.chars().collect();
+		let mut segments = Vec::new();
+		if chars.len() <= MAX_PHRASE_LENGTH {
+			for start in 0..chars.len() {
+				let end = std::cmp::min(chars.len(), start + MAX_SEGMENT_LENGTH);
+				for end in (start + 1..=end).rev() {
+					let segment = Segment {
+						text: chars[start..end].iter().collect(),
+						start,
+						end,
+						partial: end == chars.len(),
+					};
+					self.add(&segment.text, segment.partial);
+					segments.push(segment);
+				}
+			}
+		}
+
+		// Sort the segments by the largest match first and then position.
+		segments.sort_by(|a, b| {
+			let (la, lb) = (a.end - a.start, b.end - b.start);
+			lb.cmp(&la)
+				.then(a.start.cmp(&b.start))
+				.then(b.end.cmp(&a.end))
+		});
+		self.segments.insert(phrase.to_string(), segments);
+	}
+
+	/// De-inflects all words in a phrase previously added with [add_phrase],
+	/// returning the matched entries sorted by their position in the phrase.
+	///
+	/// Segments are matched from the largest, and once a segment matches any
+	/// overlapping segment is discarded.
+	///
+	/// [add_phrase]: Deinflector::add_phrase
+	pub fn deinflect_all<'b>(&self, dict: &'b Dict, phrase: &str) -> Vec<Match<'b>> {
+		let mut output: Vec<(usize, Vec<Match>)> = Vec::new();
+		let mut segments: Vec<&Segment> = match self.segments.get(phrase) {
+			Some(segments) => segments.iter().collect(),
+			None => Vec::new(),
+		};
+
+		while !segments.is_empty() {
+			let segment = segments.remove(0);
+			let matches = self.lookup(dict, &segment.text, segment.start, segment.partial);
+			if !matches.is_empty() {
+				output.push((segment.start, matches));
+				segments.retain(|it| it.end <= segment.start || it.start >= segment.end);
+			}
+		}
+
+		output.sort_by_key(|(position, _)| *position);
+
+		let mut added = HashSet::new();
+		output
+			.into_iter()
+			.flat_map(|(_, matches)| matches)
+			.filter(|m| added.insert(m.entry.position))
+			.collect()
+	}
+
+	/// Looks up the entries for the best de-inflection of a term previously
+	/// added with [add], returning an empty list if the term does not match
+	/// any word. The `position` is the position of the term in the query.
+	///
+	/// [add]: Deinflector::add
+	pub fn lookup<'b>(
+		&self,
+		dict: &'b Dict,
+		term: &str,
+		position: usize,
+		allow_partial: bool,
+	) -> Vec<Match<'b>> {
+		let key = (term.to_string(), allow_partial);
+		let candidates = match self.by_source.get(&key) {
+			Some(candidates) => candidates,
+			None => return Vec::new(),
+		};
+
+		let best = candidates
+			.iter()
+			.filter_map(|it| {
+				let entries: Vec<&Entry> = dict
+					.exact(&it.term)
+					.into_iter()
+					.filter(|x| it.rules.is_empty() || x.has_rule_tag(it.rules))
+					.collect();
+				if entries.is_empty() {
+					None
+				} else {
+					Some((it, entries))
+				}
+			})
+			.min_by_key(|(it, entries)| {
+				// Favor full de-inflections or with smaller completed suffixes,
+				// then the shortest number of rules applied, then the shortest
+				// prefix (i.e. longest inflection), and then the least number
+				// of entries (more specific).
+				let partial = it.partial.chars().count();
+				let prefix = it.prefix.chars().count();
+				(partial, it.reasons.len(), prefix, entries.len())
+			});
+
+		let (candidate, mut entries) = match best {
+			Some(best) => best,
+			None => return Vec::new(),
+		};
+
+		// If this is a single kana input, limit the output to matching
+		// kana-only entries.
+		if kana::is_kana_text(term) && term.chars().count() == 1 {
+			entries.retain(|x| x.kanji.is_empty() && x.read() == term);
+		}
+
+		// Try to further limit the matching entries.
+		let filter_if = |entries: &mut Vec<&Entry>, cond: &dyn Fn(&Entry) -> bool| {
+			if entries.iter().any(|x| cond(x)) {
+				entries.retain(|x| cond(x));
+			}
+		};
+		filter_if(&mut entries, &|x| {
+			x.kanji.first().map(|k| k.expr == term).unwrap_or(false) || x.read() == term
+		});
+		if !kana::is_kana_text(term) {
+			filter_if(&mut entries, &|x| x.popular());
+		}
+
+		let (prefix, suffix) =
+			inflection_info(&candidate.term, &candidate.reasons, &candidate.partial);
+		let rules: Vec<String> = candidate.reasons.iter().map(|x| x.name.clone()).collect();
+		entries
+			.into_iter()
+			.map(|entry| Match {
+				entry,
+				query: term.to_string(),
+				position,
+				text: candidate.term.clone(),
+				prefix: prefix.clone(),
+				suffix: suffix.clone(),
+				rules: rules.clone(),
+			})
+			.collect()
+	}
+
+	/// Looks up the dictionary entries for all candidates, returning only the
+	/// valid de-inflections sorted by the entry position.
+	pub fn filter<'b>(&self, dict: &'b Dict) -> Vec<Match<'b>> {
+		filter_candidates(dict, self.map.values().flatten())
+	}
+
+	/// Same as [Deinflector::filter], but only for the candidates of a term
+	/// added with [Deinflector::add].
+	pub fn filter_term<'b>(
+		&self,
+		dict: &'b Dict,
+		term: &str,
+		allow_partial: bool,
+	) -> Vec<Match<'b>> {
+		let key = (term.to_string(), allow_partial);
+		match self.by_source.get(&key) {
+			Some(candidates) => filter_candidates(dict, candidates.iter()),
+			None => Vec::new(),
+		}
+	}
+
+	fn candidates(&self, source: &str, allow_partial: bool) -> Vec<Candidate<'a>> {
+		// Based on https://github.com/FooSoft/yomichan/blob/f68ad1f843607d4ba1ad216fe16305c420cee8d6/ext/js/language/deinflector.js#L23
+		let mut queue = vec![Candidate {
+			term: source.to_string(),
+			source: source.to_string(),
+			position: 0,
+			partial: String::new(),
+			prefix: source.to_string(),
+			rules: &[],
+			reasons: Vec::new(),
+		}];
+
+		let mut index = 0;
+		while index < queue.len() {
+			// Do we allow partial de-inflection? (i.e. for a partially typed
+			// suffix)
+			let can_partial = allow_partial && index == 0;
+
+			// Try to match the current term with all possible rules.
+			let mut next = Vec::new();
+			let current = &queue[index];
+			let input = current.term.as_str();
+			for rule in self.rules.list() {
+				let src = rule.kana_in.as_str();
+				let dst = rule.kana_out.as_str();
+
+				// Use the term suffix to check if the rule is applicable.
+				let mut matched = if input.ends_with(src) { src } else { "" };
+				let mut partial = "";
+				if matched.is_empty() && can_partial {
+					let prefixes = src.char_indices().rev().map(|(pos, _)| pos);
+					for pos in prefixes.filter(|&pos| pos > 0) {
+						if input.ends_with(&src[..pos]) {
+							matched = &src[..pos];
+							partial = &src[pos..];
+							break;
+						}
+					}
+				}
+
+				let prefix = &input[..input.len() - matched.len()];
+				if matched.is_empty() || (prefix.is_empty() && dst.is_empty()) {
+					continue; // we didn't match or resulted in an empty term
+				}
+
+				let rules = current.rules;
+				if !rules.is_empty() && !rule.rules_in.iter().any(|x| rules.contains(x)) {
+					continue; // grammar rules don't apply to the current term
+				}
+
+				let mut reasons = vec![rule];
+				reasons.extend(current.reasons.iter().cloned());
+				next.push(Candidate {
+					term: format!("{}{}", prefix, dst),
+					source: current.source.clone(),
+					position: current.position,
+					partial: if current.partial.is_empty() {
+						partial.to_string()
+					} else {
+						current.partial.clone()
+					},
+					prefix: prefix.to_string(),
+					rules: &rule.rules_out,
+					reasons,
+				});
+			}
+
+			queue.extend(next);
+			index += 1;
+		}
+		queue
+	}
+}
+
+/// Looks up the dictionary entries for the candidates, returning the best
+/// valid de-inflection for each entry sorted by the entry position.
+fn filter_candidates<'a, 'b, 'c, I>(dict: &'b Dict, candidates: I) -> Vec<Match<'b>>
+where
+	'a: 'c,
+	I: Iterator<Item = &'c Rc<Candidate<'a>>>,
+{
+	// Collect the candidate de-inflections for each entry.
+	let mut entries: HashMap<usize, &Entry> = HashMap::new();
+	let mut by_entry: HashMap<usize, Vec<&Candidate>> = HashMap::new();
+	for it in candidates {
+		for entry in dict.exact(&it.term) {
+			entries.insert(entry.position, entry);
+			let list = by_entry.entry(entry.position).or_default();
+			if it.rules.is_empty() || entry.has_rule_tag(it.rules) {
+				list.push(it);
+			}
+		}
+	}
+
+	let mut output = Vec::new();
+	for (position, candidates) in by_entry {
+		let best = candidates.into_iter().min_by_key(|it| {
+			// Favor full de-inflections or with smaller completed suffixes,
+			// then the shortest number of rules applied, and then the
+			// shortest expressions.
+			let partial = it.partial.chars().count();
+			(partial, it.reasons.len(), it.term.chars().count())
+		});
+		if let Some(it) = best {
+			let (prefix, suffix) = inflection_info(&it.term, &it.reasons, &it.partial);
+			output.push(Match {
+				entry: entries[&position],
+				query: it.source.clone(),
+				position: it.position,
+				text: it.term.clone(),
+				prefix,
+				suffix,
+				rules: it.reasons.iter().map(|x| x.name.clone()).collect(),
+			});
+		}
+	}
+	output.sort_by_key(|x| x.entry.position);
+	output
+}
+
+/// Returns the uninflected prefix and the inflected suffix for a de-inflected
+/// expression given the rules applied.
+fn inflection_info(expr: &str, reasons: &[&Rule], partial: &str) -> (String, String) {
+	let first = match reasons.first() {
+		Some(first) => first,
+		None => return (expr.to_string(), String::new()),
+	};
+
+	debug_assert!(
+		expr.ends_with(&first.kana_out),
+		"expression has rule suffix"
+	);
+	let prefix = &expr[..expr.len() - first.kana_out.len()];
+
+	let mut suffix = first.kana_out.clone();
+	for it in reasons {
+		debug_assert!(
+			suffix.ends_with(&it.kana_out),
+			"inflection rule and suffix match"
+		);
+		suffix = format!(
+			"{}{}",
+			&suffix[..suffix.len() - it.kana_out.len()],
+			it.kana_in
+		);
+	}
+
+	if !partial.is_empty() && suffix.ends_with(partial) {
+		let pos = suffix.len() - partial.len();
+		suffix = format!("{}.{}", &suffix[..pos], &suffix[pos..]);
+	}
+
+	(prefix.to_string(), suffix)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const RULES: &str = r#"
+		// comment
+		{
+			"past": [
+				{ "kanaIn": "た", "kanaOut": "る", "rulesIn": [], "rulesOut": ["v1"] },
+				{ "kanaIn": "かった", "kanaOut": "い", "rulesIn": [], "rulesOut": ["adj-i"] }
+			],
+			"negative": [
+				{ "kanaIn": "ない", "kanaOut": "る", "rulesIn": ["adj-i"], "rulesOut": ["v1"] }
+			],
+			"polite": [
+				{ "kanaIn": "ます", "kanaOut": "る", "rulesIn": [], "rulesOut": ["v1"] }
+			],
+			"test": [
+				{ "kanaIn": "ろ", "kanaOut": "ない", "rulesIn": [], "rulesOut": ["v1"] }
+			]
+		}
+	"#;
+
+	fn candidates(input: &str, allow_partial: bool) -> Vec<(String, Vec<String>, String)> {
+		let rules = Rules::parse(RULES).unwrap();
+		let deinflector = Deinflector::new(&rules);
+		deinflector
+			.candidates(input, allow_partial)
+			.into_iter()
+			.map(|x| {
+				let names = x.reasons.iter().map(|x| x.name.clone()).collect();
+				let (_, suffix) = inflection_info(&x.term, &x.reasons, &x.partial);
+				(x.term, names, suffix)
+			})
+			.collect()
+	}
+
+	fn item(term: &str, rules: &[&str], suffix: &str) -> (String, Vec<String>, String) {
+		let rules = rules.iter().map(|x| x.to_string()).collect();
+		(term.to_string(), rules, suffix.to_string())
+	}
+
+	#[test]
+	fn parses_rules_in_order() {
+		let rules = Rules::parse(RULES).unwrap();
+		let names: Vec<&str> = rules.list().iter().map(|x| x.name.as_str()).collect();
+		assert_eq!(names, vec!["past", "past", "negative", "polite", "test"]);
+		assert_eq!(rules.list()[1].kana_in, "かった");
+		assert_eq!(rules.list()[2].rules_in, vec!["adj-i"]);
+	}
+
+	#[test]
+	fn generates_candidates() {
+		assert_eq!(
+			candidates("たべた", false),
+			vec![item("たべた", &[], ""), item("たべる", &["past"], "た")]
+		);
+		assert_eq!(
+			candidates("たべなかった", false),
+			vec![
+				item("たべなかった", &[], ""),
+				item("たべなかっる", &["past"], "た"),
+				item("たべない", &["past"], "かった"),
+				item("たべる", &["negative", "past"], "なかった"),
+			]
+		);
+	}
+
+	#[test]
+	fn checks_rules_in() {
+		// The `negative` rule applies to any term, but when chained only to
+		// `adj-i` terms.
+		assert_eq!(
+			candidates("たべない", false),
+			vec![
+				item("たべない", &[], ""),
+				item("たべる", &["negative"], "ない")
+			]
+		);
+		assert_eq!(
+			candidates("たべろ", false),
+			vec![item("たべろ", &[], ""), item("たべない", &["test"], "ろ")]
+		);
+	}
+
+	#[test]
+	fn adds_phrase_segments() {
+		let rules = Rules::parse(RULES).unwrap();
+		let mut deinflector = Deinflector::new(&rules);
+		deinflector.add_phrase("あいう");
+		let segments: Vec<(&str, usize, bool)> = deinflector.segments["あいう"]
+			.iter()
+			.map(|x| (x.text.as_str(), x.start, x.partial))
+			.collect();
+		assert_eq!(
+			segments,
+			vec![
+				("あいう", 0, true),
+				("あい", 0, false),
+				("いう", 1, true),
+				("あ", 0, false),
+				("い", 1, false),
+				("う", 2, true),
+			]
+		);
+	}
+
+	#[test]
+	fn generates_partial_candidates() {
+		assert_eq!(
+			candidates("たべま", true),
+			vec![
+				item("たべま", &[], ""),
+				item("たべる", &["polite"], "ま.す")
+			]
+		);
+		assert_eq!(candidates("たべま", false), vec![item("たべま", &[], "")]);
+	}
+}
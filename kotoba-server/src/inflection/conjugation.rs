@@ -0,0 +1,400 @@
+use crate::dict::Entry;
+use crate::kana;
+
+use super::{Rule, Rules};
+
+/// Conjugated form of a dictionary entry.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Inflection {
+	/// Name of the conjugation (e.g. `polite`, `negative past`).
+	pub name: String,
+
+	/// Names of the inflection rules applied to the dictionary form, in
+	/// order. Empty for na-adjectives, which are conjugated through the
+	/// copula.
+	pub rules: Vec<String>,
+
+	/// Conjugated form of the entry word.
+	pub word: String,
+
+	/// Conjugated form of the entry reading.
+	pub read: String,
+}
+
+/// Rows for the conjugation table.
+///
+/// Each row has a list of alternative rule sequences, applied in order from
+/// the dictionary form. The first sequence that applies to the entry is used.
+const TABLE: &[(&str, &[&[&str]])] = &[
+	("masu stem", &[&["masu stem"]]),
+	("polite", &[&["polite"]]),
+	("negative", &[&["negative"]]),
+	("polite negative", &[&["polite negative"]]),
+	("past", &[&["past"]]),
+	("negative past", &[&["negative", "past"]]),
+	("polite past", &[&["polite past"]]),
+	("polite past negative", &[&["polite past negative"]]),
+	("-te", &[&["-te"]]),
+	("negative -te", &[&["negative", "-te"]]),
+	("-ba", &[&["-ba"]]),
+	("negative -ba", &[&["negative", "-ba"]]),
+	("-tara", &[&["-tara"]]),
+	("-tai", &[&["-tai"]]),
+	("potential", &[&["potential or passive"], &["potential"]]),
+	("passive", &[&["passive"], &["potential or passive"]]),
+	("causative", &[&["causative"]]),
+	(
+		"causative passive",
+		&[
+			&["causative passive"],
+			&["causative", "potential or passive"],
+		],
+	),
+	("volitional", &[&["volitional"]]),
+	("polite volitional", &[&["polite volitional"]]),
+	("imperative", &[&["imperative"]]),
+	("imperative negative", &[&["imperative negative"]]),
+	("adv", &[&["adv"]]),
+	("noun", &[&["noun"]]),
+];
+
+/// Conjugations for na-adjectives, which are not part of the inflection
+/// rules. The suffix is appended to the dictionary form.
+const NA_ADJECTIVE: &[(&str, &str)] = &[
+	("polite", "です"),
+	("negative", "じゃない"),
+	("polite negative", "じゃありません"),
+	("past", "だった"),
+	("negative past", "じゃなかった"),
+	("polite past", "でした"),
+	("-te", "で"),
+	("-ba", "なら"),
+	("adv", "に"),
+];
+
+impl Rules {
+	/// Generates the conjugation table for a verb or adjective entry. Returns
+	/// an empty list for entries that cannot be conjugated.
+	///
+	/// This applies the inflection rules in reverse, so it is the inverse of
+	/// the de-inflection. The rules are applied to the reading, which is then
+	/// re-attached to the kanji stem of the word.
+	pub fn conjugate(&self, entry: &Entry) -> Vec<Inflection> {
+		let has_pos = |name: &str| entry.sense.iter().any(|x| x.pos.iter().any(|p| p == name));
+
+		// Suru-verbs are nouns in the dictionary, so we add the verb.
+		let (word, read) = if has_pos("vs") && !entry.read().ends_with("する") {
+			(
+				format!("{}する", entry.word()),
+				format!("{}する", entry.read()),
+			)
+		} else {
+			(entry.word().to_string(), entry.read().to_string())
+		};
+
+		// The `いい` adjective is conjugated from `よい` (e.g. `よくない`).
+		let (word, read) = if has_pos("adj-ix") {
+			let yoi = |text: String| match text.strip_suffix("いい") {
+				Some(prefix) => format!("{}よい", prefix),
+				None => text,
+			};
+			(yoi(word), yoi(read))
+		} else {
+			(word, read)
+		};
+
+		let mut output = Vec::new();
+		for &(name, sequences) in TABLE {
+			let inflection = sequences.iter().find_map(|sequence| {
+				let (conjugated, rules) = self.apply(entry, &read, sequence)?;
+				let word = if word == read {
+					conjugated.clone()
+				} else {
+					self.attach(entry, &word, &read, &conjugated, sequence)?
+				};
+				Some(Inflection {
+					name: name.to_string(),
+					rules,
+					word,
+					read: conjugated,
+				})
+			});
+			output.extend(inflection);
+		}
+
+		// The rules have no masu stem for suru-verbs, so we derive it from the
+		// polite form.
+		if !output.iter().any(|x| x.name == "masu stem") {
+			let stem = output.iter().find(|x| x.name == "polite").and_then(|x| {
+				Some(Inflection {
+					name: String::from("masu stem"),
+					rules: x.rules.clone(),
+					word: x.word.strip_suffix("ます")?.to_string(),
+					read: x.read.strip_suffix("ます")?.to_string(),
+				})
+			});
+			if let Some(stem) = stem {
+				output.insert(0, stem);
+			}
+		}
+
+		if has_pos("adj-na") {
+			output.extend(NA_ADJECTIVE.iter().map(|&(name, suffix)| Inflection {
+				name: name.to_string(),
+				rules: Vec::new(),
+				word: format!("{}{}", entry.word(), suffix),
+				read: format!("{}{}", entry.read(), suffix),
+			}));
+		}
+		output
+	}
+
+	/// Re-attaches the conjugated reading to the kanji stem of the word.
+	///
+	/// The part of the reading changed by the rules replaces the same kana at
+	/// the end of the word. A word written only in kanji keeps the kanji and
+	/// gets the new kana appended. Otherwise the rules changed the reading of
+	/// the kanji itself (e.g. `来る` to `こない`), so they are applied to the
+	/// word, which only works for rules written with the kanji.
+	fn attach(
+		&self,
+		entry: &Entry,
+		word: &str,
+		read: &str,
+		conjugated: &str,
+		sequence: &[&str],
+	) -> Option<String> {
+		let same: usize = read
+			.chars()
+			.zip(conjugated.chars())
+			.take_while(|(a, b)| a == b)
+			.map(|(chr, _)| chr.len_utf8())
+			.sum();
+		let (changed, added) = (&read[same..], &conjugated[same..]);
+		if let Some(stem) = word.strip_suffix(changed) {
+			Some(format!("{}{}", stem, added))
+		} else if !word.chars().any(kana::is_kana) {
+			Some(format!("{}{}", word, added))
+		} else {
+			self.apply(entry, word, sequence).map(|(word, _)| word)
+		}
+	}
+
+	/// Applies the sequence of rules to the dictionary form of the entry.
+	///
+	/// The first rule must apply to the grammar tags of the entry, while
+	/// the next ones must apply to the form generated by the previous rule.
+	fn apply(&self, entry: &Entry, text: &str, sequence: &[&str]) -> Option<(String, Vec<String>)> {
+		let mut text = text.to_string();
+		let mut last: Option<&Rule> = None;
+		let mut names = Vec::new();
+		for &name in sequence {
+			let rule = self
+				.list()
+				.iter()
+				.filter(|rule| rule.name == name && text.ends_with(&rule.kana_out))
+				.filter(|rule| match last {
+					None => entry.has_rule_tag(&rule.rules_out),
+					Some(last) => rule.rules_out.iter().any(|x| last.rules_in.contains(x)),
+				})
+				// Favor the most specific rule (e.g. `行く` over `く`).
+				.fold(None, |best: Option<&Rule>, rule| match best {
+					Some(best) if best.kana_out.len() >= rule.kana_out.len() => Some(best),
+					_ => Some(rule),
+				})?;
+			let prefix = &text[..text.len() - rule.kana_out.len()];
+			text = format!("{}{}", prefix, rule.kana_in);
+			names.push(rule.name.clone());
+			last = Some(rule);
+		}
+		Some((text, names))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::dict::{EntryKanji, EntryReading, EntrySense};
+
+	const RULES: &str = r#"{
+		"negative": [
+			{ "kanaIn": "くない", "kanaOut": "い", "rulesIn": ["adj-i"], "rulesOut": ["adj-i"] },
+			{ "kanaIn": "ない", "kanaOut": "る", "rulesIn": ["adj-i"], "rulesOut": ["v1"] },
+			{ "kanaIn": "かない", "kanaOut": "く", "rulesIn": ["adj-i"], "rulesOut": ["v5"] }
+		],
+		"past": [
+			{ "kanaIn": "かった", "kanaOut": "い", "rulesIn": [], "rulesOut": ["adj-i"] },
+			{ "kanaIn": "た", "kanaOut": "る", "rulesIn": [], "rulesOut": ["v1"] },
+			{ "kanaIn": "いた", "kanaOut": "く", "rulesIn": [], "rulesOut": ["v5"] },
+			{ "kanaIn": "いった", "kanaOut": "いく", "rulesIn": [], "rulesOut": ["v5"] },
+			{ "kanaIn": "行った", "kanaOut": "行く", "rulesIn": [], "rulesOut": ["v5"] },
+			{ "kanaIn": "した", "kanaOut": "する", "rulesIn": [], "rulesOut": ["vs"] }
+		]
+	}"#;
+
+	fn entry(word: &str, read: &str, pos: &str) -> Entry {
+		let mut entry = Entry::default();
+		if word != read {
+			entry.kanji.push(EntryKanji {
+				expr: word.to_string(),
+				..Default::default()
+			});
+		}
+		entry.reading.push(EntryReading {
+			expr: read.to_string(),
+			..Default::default()
+		});
+		entry.sense.push(EntrySense {
+			pos: vec![pos.to_string()],
+			..Default::default()
+		});
+		entry
+	}
+
+	fn conjugate(word: &str, read: &str, pos: &str) -> Vec<(String, String, String)> {
+		let rules = Rules::parse(RULES).unwrap();
+		rules
+			.conjugate(&entry(word, read, pos))
+			.into_iter()
+			.map(|x| (x.name, x.word, x.read))
+			.collect()
+	}
+
+	fn row(name: &str, word: &str, read: &str) -> (String, String, String) {
+		(name.to_string(), word.to_string(), read.to_string())
+	}
+
+	#[test]
+	fn conjugates_verbs() {
+		assert_eq!(
+			conjugate("食べる", "たべる", "v1"),
+			vec![
+				row("negative", "食べない", "たべない"),
+				row("past", "食べた", "たべた"),
+				row("negative past", "食べなかった", "たべなかった"),
+			]
+		);
+		assert_eq!(
+			conjugate("書く", "かく", "v5k"),
+			vec![
+				row("negative", "書かない", "かかない"),
+				row("past", "書いた", "かいた"),
+				row("negative past", "書かなかった", "かかなかった"),
+			]
+		);
+	}
+
+	#[test]
+	fn uses_most_specific_rule() {
+		assert_eq!(
+			conjugate("行く", "いく", "v5k-s")[1],
+			row("past", "行った", "いった")
+		);
+	}
+
+	#[test]
+	fn conjugates_adjectives() {
+		assert_eq!(
+			conjugate("高い", "たかい", "adj-i"),
+			vec![
+				row("negative", "高くない", "たかくない"),
+				row("past", "高かった", "たかかった"),
+				row("negative past", "高くなかった", "たかくなかった"),
+			]
+		);
+		assert_eq!(
+			conjugate("静か", "しずか", "adj-na")[0],
+			row("polite", "静かです", "しずかです")
+		);
+	}
+
+	#[test]
+	fn conjugates_ii_as_yoi() {
+		assert_eq!(
+			conjugate("良い", "いい", "adj-ix"),
+			vec![
+				row("negative", "良くない", "よくない"),
+				row("past", "良かった", "よかった"),
+				row("negative past", "良くなかった", "よくなかった"),
+			]
+		);
+		assert_eq!(
+			conjugate("いい", "いい", "adj-ix")[0],
+			row("negative", "よくない", "よくない")
+		);
+	}
+
+	#[test]
+	fn conjugates_words_written_only_in_kanji() {
+		assert_eq!(
+			conjugate("書", "かく", "v5k"),
+			vec![
+				row("negative", "書かない", "かかない"),
+				row("past", "書いた", "かいた"),
+				row("negative past", "書かなかった", "かかなかった"),
+			]
+		);
+	}
+
+	#[test]
+	fn conjugates_suru_verbs() {
+		assert_eq!(
+			conjugate("勉強", "べんきょう", "vs"),
+			vec![row("past", "勉強した", "べんきょうした")]
+		);
+	}
+
+	#[test]
+	fn conjugates_with_grammar_rules() {
+		let filename =
+			std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../data/grammar/deinflect.json");
+		let rules = Rules::load(&filename).unwrap();
+		let table = |entry: &Entry| -> Vec<(String, String, String)> {
+			let list = rules.conjugate(entry).into_iter();
+			list.map(|x| (x.name, x.word, x.read)).collect()
+		};
+
+		let list = table(&entry("食べる", "たべる", "v1"));
+		assert_eq!(list[0], row("masu stem", "食べ", "たべ"));
+		assert!(list.contains(&row("passive", "食べられる", "たべられる")));
+
+		let list = table(&entry("書く", "かく", "v5k"));
+		assert_eq!(list[0], row("masu stem", "書き", "かき"));
+		assert!(list.contains(&row("-te", "書いて", "かいて")));
+		assert!(list.contains(&row("potential", "書ける", "かける")));
+
+		let list = table(&entry("来る", "くる", "vk"));
+		assert_eq!(list[0], row("masu stem", "来", "き"));
+		assert!(list.contains(&row("negative", "来ない", "こない")));
+		assert!(list.contains(&row("imperative", "来い", "こい")));
+
+		let list = table(&entry("為る", "する", "vs-i"));
+		assert_eq!(list[0], row("masu stem", "為", "し"));
+		assert!(list.contains(&row("volitional", "為よう", "しよう")));
+
+		let list = table(&entry("乞う", "こう", "v5u-s"));
+		assert!(list.contains(&row("past", "乞うた", "こうた")));
+		assert!(list.contains(&row("-te", "乞うて", "こうて")));
+
+		let list = table(&entry("良い", "いい", "adj-ix"));
+		assert!(list.contains(&row("negative", "良くない", "よくない")));
+		assert!(list.contains(&row("-te", "良くて", "よくて")));
+
+		let list = table(&entry("静か", "しずか", "adj-na"));
+		assert_eq!(list.len(), NA_ADJECTIVE.len());
+		assert!(list.contains(&row("adv", "静かに", "しずかに")));
+
+		// Na-adjectives that are also suru-verbs have both conjugations.
+		let mut noun = entry("安心", "あんしん", "vs");
+		noun.sense[0].pos.push(String::from("adj-na"));
+		let list = table(&noun);
+		assert_eq!(list[0], row("masu stem", "安心し", "あんしんし"));
+		assert!(list.contains(&row("past", "安心だった", "あんしんだった")));
+	}
+
+	#[test]
+	fn ignores_other_entries() {
+		assert_eq!(conjugate("猫", "ねこ", "n"), vec![]);
+		assert_eq!(conjugate("する", "する", "n"), vec![]);
+	}
+}
@@ -0,0 +1,169 @@
+//! Conversion of romaji and katakana to hiragana.
+//!
+//! This is a port of `lib-ts/kana`, using the same rule tables for the
+//! conversion.
+
+mod chars;
+mod conversion;
+mod key;
+mod mora;
+mod rules;
+
+pub use chars::*;
+pub use key::*;
+pub use mora::*;
+
+use conversion::{convert, CompiledRuleSet};
+
+lazy_static! {
+	static ref TO_HIRAGANA: CompiledRuleSet = rules::rules_to_hiragana().compile();
+	static ref FULLWIDTH_TO_ASCII: CompiledRuleSet = rules::rules_fullwidth_to_ascii().compile();
+	static ref ASCII_TO_FULLWIDTH: CompiledRuleSet = rules::rules_ascii_to_fullwidth().compile();
+}
+
+/// Converts the input text to hiragana.
+///
+/// This works on any mix of romaji and katakana inputs. It will also convert
+/// romaji punctuation and spacing to the Japanese equivalents. Remaining ASCII
+/// letters and digits are converted to fullwidth.
+pub fn to_hiragana(input: &str) -> String {
+	let output = convert(&fullwidth_katakana(input), &TO_HIRAGANA);
+	convert(&output, &ASCII_TO_FULLWIDTH)
+}
+
+/// Converts fullwidth letters, digits, and punctuation to ASCII.
+pub fn fullwidth_to_ascii(input: &str) -> String {
+	convert(input, &FULLWIDTH_TO_ASCII)
+}
+
+/// Converts halfwidth katakana to fullwidth.
+pub fn fullwidth_katakana(input: &str) -> String {
+	const HALFWIDTH: &str = "ｰｦｧｨｩｪｫｬｭｮｯｱｲｳｴｵｶｷｸｹｺｻｼｽｾｿﾀﾁﾂﾃﾄﾅﾆﾇﾈﾉﾊﾋﾌﾍﾎﾏﾐﾑﾒﾓﾔﾕﾖﾗﾘﾙﾚﾛﾜﾝ";
+	const FULLWIDTH: &str = "ーヲァィゥェォャュョッアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン";
+	input
+		.chars()
+		.map(|chr| match HALFWIDTH.chars().position(|x| x == chr) {
+			Some(index) => FULLWIDTH.chars().nth(index).unwrap(),
+			None => chr,
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn check<F: Fn(&str) -> String>(convert: F, input: &str, expected: &str) {
+		assert_eq!(convert(input), expected, "input: {}", input);
+	}
+
+	#[test]
+	fn to_hiragana_converts_manual_cases() {
+		assert_eq!(to_hiragana(""), "");
+		check(to_hiragana, "quaqqua", "くぁっくぁ");
+		check(to_hiragana, "n", "ん");
+		check(to_hiragana, "n'", "ん");
+		check(to_hiragana, "nn", "ん");
+		check(to_hiragana, "nnX", "んんＸ");
+		check(to_hiragana, "n'n", "んん");
+		check(to_hiragana, "shinnyuu", "しんにゅう");
+	}
+
+	#[test]
+	fn to_hiragana_converts_romaji() {
+		let cases = [
+			(
+				"しゃぎゃつっじゃあんなん　んあんんざ　ｘｚｍ",
+				"shagyatsujjaan'nan n'an'nza xzm",
+			),
+			("あーいーうーえーおー", "a-i-u-e-o-"),
+			("くぁー", "qua-"),
+			("ばっば", "babba"),
+			("ちゃっちゃ", "chaccha"),
+			("なんな", "nan'na"),
+			("なんな", "nanna"),
+			("つっつ", "tsuttsu"),
+			("ゔぁっゔぁ", "vavva"),
+			("そうしんうぃんどう", "soushinWINDOU"),
+			("ああんいぇああ", "aan'yeaa"),
+			("ゔぁゔぃゔゔぇゔぉ", "vavivuvevo"),
+			("っっべあ", "bbbea"),
+			("ぶっつうじ", "buttsuuji"),
+			(
+				"わにかに　あいうえお　鰐蟹　１２３４５　＠＃＄％",
+				"wanikani aiueo 鰐蟹 12345 @#$%",
+			),
+			("座禅「ざぜん」すたいる", "座禅[zazen]sutairu"),
+			("ばつげーむ", "batsuge-mu"),
+			("ううぃのおくやま", "uwinookuyama"),
+			("うぇひもせすん", "wehimosesun"),
+			("わにかに　が　すごい　だ", "WANIKANI GA SUGOI DA"),
+			("わにかに　が　すごい　だ", "WANIKANI ga sugoi da"),
+			("罰げーむ・ばつげーむ", "罰GE-MU/batsuge-mu"),
+			("きんにくまん", "kin'nikuman"),
+			("んんにんにんにゃんやん", "n'n'nin'nin'nyan'yan"),
+			(
+				"かっぱ　たった　しゅっしゅ　ちゃっちゃ　やっつ",
+				"kappa tatta shusshu chaccha yattsu",
+			),
+			("おんよみ", "on'yomi"),
+			("んよ　んあ　んゆ", "n'yo n'a n'yu"),
+			("あっっっか", "akkkka"),
+			("あっか", "akka"),
+			("かあ", "ka'a"),
+			("かー", "kâ"),
+			("かあ", "kaa"),
+			// Small kana
+			("っ", "xtsu"),
+			("ゃ", "xya"),
+			("ぁ", "xa"),
+			("ゕ", "xKA"),
+			("ゎ", "xwa"),
+			("ゖ", "xke"),
+			// Ambiguous romaji
+			("んな", "n'na"),
+			("んな", "N'NA"),
+			("んや", "n'ya"),
+			("んあ", "n'a"),
+			("んう", "N'U"),
+		];
+		for (expected, input) in cases.iter() {
+			check(to_hiragana, input, expected);
+		}
+	}
+
+	#[test]
+	fn to_hiragana_converts_katakana() {
+		check(
+			to_hiragana,
+			"アイウエオ カキクケコ ガギグゲゴ サシスセソ ザジズゼゾ タチツテト ダヂヅデド ナニヌネノ ハヒフヘホ バビブベボ パピプペポ マミムメモ ヤユヨ ラリルレロ ワヰヱヲン",
+			"あいうえお　かきくけこ　がぎぐげご　さしすせそ　ざじずぜぞ　たちつてと　だぢづでど　なにぬねの　はひふへほ　ばびぶべぼ　ぱぴぷぺぽ　まみむめも　やゆよ　らりるれろ　わゐゑをん",
+		);
+		check(
+			to_hiragana,
+			"ァィゥェォッャュョヮヵヶ",
+			"ぁぃぅぇぉっゃゅょゎゕゖ",
+		);
+		check(
+			to_hiragana,
+			"ヴヽヾヿ𛀀ヷヸヹヺ",
+			"ゔゝゞことえわ\u{3099}ゐ\u{3099}ゑ\u{3099}を\u{3099}",
+		);
+		check(
+			to_hiragana,
+			"ㇰㇱㇲㇳㇴㇵㇶㇷㇸㇹㇺㇻㇼㇽㇾㇿ",
+			"くしすとぬはひふへほむらりるれろ",
+		);
+		check(
+			to_hiragana,
+			"ハ\u{3099}ヒ\u{3099}フ\u{3099}ヘ\u{3099}ホ\u{3099} ハ\u{309A}ヒ\u{309A}フ\u{309A}ヘ\u{309A}ホ\u{309A}",
+			"ばびぶべぼ　ぱぴぷぺぽ",
+		);
+		check(to_hiragana, "ｶﾀｶﾅ", "かたかな");
+	}
+
+	#[test]
+	fn converts_fullwidth_to_ascii() {
+		assert_eq!(fullwidth_to_ascii("ＡＢＣ１２３　、。"), "ABC123 ,.");
+	}
+}
@@ -0,0 +1,130 @@
+/// Combining Katakana-Hiragana Voiced Sound Mark.
+pub const MARK_VOICED: char = '\u{3099}';
+
+/// Combining Katakana-Hiragana Semi-Voiced Sound Mark.
+pub const MARK_SEMI_VOICED: char = '\u{309A}';
+
+/// Kana with a voiced sound mark. Each of these is one code point after its
+/// unvoiced counterpart.
+const VOICED: &str =
+	"がぎぐげござじずぜぞだぢづでどばびぶべぼゞガギグゲゴザジズゼゾダヂヅデドバビブベボヾ";
+
+/// Kana with a semi-voiced sound mark. Each of these is two code points after
+/// its unvoiced counterpart.
+const SEMI_VOICED: &str = "ぱぴぷぺぽパピプペポ";
+
+/// Voiced kana that are not sequential to their unvoiced counterpart.
+const VOICED_OTHER: &[(char, char)] = &[
+	('ゔ', 'う'),
+	('ヴ', 'ウ'),
+	('ヷ', 'ワ'),
+	('ヸ', 'ヰ'),
+	('ヹ', 'ヱ'),
+	('ヺ', 'ヲ'),
+];
+
+/// Latin vowels with accents used to represent long vowels in romaji.
+const LONG_VOWELS: &[(char, char, char)] = &[
+	('â', 'a', '\u{0302}'),
+	('ê', 'e', '\u{0302}'),
+	('î', 'i', '\u{0302}'),
+	('ô', 'o', '\u{0302}'),
+	('û', 'u', '\u{0302}'),
+	('Â', 'A', '\u{0302}'),
+	('Ê', 'E', '\u{0302}'),
+	('Î', 'I', '\u{0302}'),
+	('Ô', 'O', '\u{0302}'),
+	('Û', 'U', '\u{0302}'),
+	('ā', 'a', '\u{0304}'),
+	('ē', 'e', '\u{0304}'),
+	('ī', 'i', '\u{0304}'),
+	('ō', 'o', '\u{0304}'),
+	('ū', 'u', '\u{0304}'),
+	('Ā', 'A', '\u{0304}'),
+	('Ē', 'E', '\u{0304}'),
+	('Ī', 'I', '\u{0304}'),
+	('Ō', 'O', '\u{0304}'),
+	('Ū', 'U', '\u{0304}'),
+];
+
+/// Returns true if the character is a hiragana or katakana letter, including
+/// the small, halfwidth, and rare variants, the long vowel mark, iteration
+/// marks, and the combining sound marks.
+pub fn is_kana(chr: char) -> bool {
+	matches!(chr,
+		'\u{3041}'..='\u{3096}' // Hiragana
+		| '\u{3099}'..='\u{309F}' // Combining marks, iteration marks, and ゟ
+		| '\u{30A1}'..='\u{30FA}' // Katakana
+		| '\u{30FC}'..='\u{30FF}' // ー, iteration marks, and ヿ
+		| '\u{31F0}'..='\u{31FF}' // Small katakana extension
+		| '\u{FF66}'..='\u{FF9D}' // Halfwidth katakana, including ｰ
+		| '〼' | '𛀀'
+	)
+}
+
+/// Returns true if the text is not empty and all characters are [is_kana].
+pub fn is_kana_text(text: &str) -> bool {
+	!text.is_empty() && text.chars().all(is_kana)
+}
+
+/// Splits a kana or romaji long vowel with a sound mark or accent into the
+/// base character and the combining mark. Returns [None] for any other
+/// character.
+///
+/// This is the canonical decomposition (NFD) restricted to the characters
+/// used by the kana conversion.
+pub fn decompose(chr: char) -> Option<(char, char)> {
+	let offset = |base: u32| std::char::from_u32(base).unwrap();
+	if VOICED.contains(chr) {
+		return Some((offset(chr as u32 - 1), MARK_VOICED));
+	}
+	if SEMI_VOICED.contains(chr) {
+		return Some((offset(chr as u32 - 2), MARK_SEMI_VOICED));
+	}
+	if let Some(&(_, base)) = VOICED_OTHER.iter().find(|x| x.0 == chr) {
+		return Some((base, MARK_VOICED));
+	}
+	if let Some(&(_, base, mark)) = LONG_VOWELS.iter().find(|x| x.0 == chr) {
+		return Some((base, mark));
+	}
+	None
+}
+
+/// Inverse of [decompose].
+pub fn compose(base: char, mark: char) -> Option<char> {
+	let candidates = VOICED.chars().chain(SEMI_VOICED.chars());
+	let candidates = candidates.chain(VOICED_OTHER.iter().map(|x| x.0));
+	let mut candidates = candidates.chain(LONG_VOWELS.iter().map(|x| x.0));
+	candidates.find(|&chr| decompose(chr) == Some((base, mark)))
+}
+
+/// Applies [decompose] to all characters in the text.
+pub fn to_nfd(text: &str) -> String {
+	let mut output = String::with_capacity(text.len());
+	for chr in text.chars() {
+		match decompose(chr) {
+			Some((base, mark)) => {
+				output.push(base);
+				output.push(mark);
+			}
+			None => output.push(chr),
+		}
+	}
+	output
+}
+
+/// Applies [compose] to all characters in the text.
+pub fn to_nfc(text: &str) -> String {
+	let mut output = String::with_capacity(text.len());
+	let mut chars = text.chars().peekable();
+	while let Some(chr) = chars.next() {
+		match chars.peek().and_then(|&mark| compose(chr, mark)) {
+			Some(composed) => {
+				output.push(composed);
+				chars.next();
+			}
+			None => output.push(chr),
+		}
+	}
+	output
+}
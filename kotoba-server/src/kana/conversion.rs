@@ -0,0 +1,247 @@
+//! Generic text conversion engine based on mapping rules.
+//!
+//! The engine operates on a [RuleSet] that provides a set of rules describing
+//! the expected text output based on the input text at the current position.
+//! Rule sets can be combined to achieve different conversion behaviors.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::chars::{to_nfc, to_nfd};
+
+/// Function for function-style mapping rules.
+///
+/// Returns the generated output and the length of the consumed input (in
+/// characters). An empty output or zero length will use the rule defaults.
+///
+/// Returns [None] if the rule does not apply.
+pub type RuleFn = Arc<dyn Fn(&Context) -> Option<(String, usize)> + Send + Sync>;
+
+/// Maps an input key to the output.
+#[derive(Clone)]
+pub struct Rule {
+	pub key: String,
+	pub out: String,
+
+	/// Length of the input consumed by the rule, in characters. If zero, this
+	/// is the length of the key.
+	pub len: usize,
+
+	pub func: Option<RuleFn>,
+}
+
+/// Creates a simple rule mapping the input key to the output.
+pub fn m<S1: Into<String>, S2: Into<String>>(key: S1, out: S2) -> Rule {
+	m_len(key, out, 0)
+}
+
+/// Same as [m] but with an explicit length of consumed input.
+pub fn m_len<S1: Into<String>, S2: Into<String>>(key: S1, out: S2, len: usize) -> Rule {
+	Rule {
+		key: key.into(),
+		out: out.into(),
+		len,
+		func: None,
+	}
+}
+
+/// Creates a function-style rule.
+pub fn m_fn<S: Into<String>, F>(key: S, func: F) -> Rule
+where
+	F: Fn(&Context) -> Option<(String, usize)> + Send + Sync + 'static,
+{
+	Rule {
+		key: key.into(),
+		out: String::new(),
+		len: 0,
+		func: Some(Arc::new(func)),
+	}
+}
+
+/// Context for the rule being applied.
+pub struct Context<'a> {
+	/// Remaining input after the current rule.
+	pub next_input: &'a str,
+
+	/// Input to the last rule matched.
+	pub last_input: &'a str,
+}
+
+/// Collection of text transformation rules.
+///
+/// Later rules override previous ones with the same key, i.e. the sequence
+/// is in increasing order of precedence.
+#[derive(Clone, Default)]
+pub struct RuleSet {
+	rules: Vec<Rule>,
+}
+
+impl RuleSet {
+	pub fn new() -> RuleSet {
+		RuleSet::default()
+	}
+
+	/// Appends a rule to the set.
+	pub fn add(mut self, rule: Rule) -> RuleSet {
+		self.rules.push(rule);
+		self
+	}
+
+	/// Appends a list of rules to the set.
+	pub fn add_all<T: IntoIterator<Item = Rule>>(mut self, rules: T) -> RuleSet {
+		self.rules.extend(rules);
+		self
+	}
+
+	/// Appends the rules from another set.
+	pub fn merge(mut self, other: RuleSet) -> RuleSet {
+		self.rules.extend(other.rules);
+		self
+	}
+
+	/// Maps every rule in the set using the given mapper.
+	pub fn transform<F: Fn(&Rule) -> Vec<Rule>>(mut self, mapper: F) -> RuleSet {
+		self.rules = self.rules.iter().flat_map(mapper).collect();
+		self
+	}
+
+	/// Compiles the set to be used with [convert].
+	pub fn compile(self) -> CompiledRuleSet {
+		let mut mappings = HashMap::new();
+		for rule in self.rules {
+			// Expand the keys to include their normalized versions.
+			let nfc = to_nfc(&rule.key);
+			let nfd = to_nfd(&rule.key);
+			if nfc != rule.key {
+				mappings.insert(nfc, rule.clone());
+			}
+			if nfd != rule.key {
+				mappings.insert(nfd, rule.clone());
+			}
+			mappings.insert(rule.key.clone(), rule);
+		}
+
+		// Maximum key length given the first character of the input.
+		let mut max_length = HashMap::new();
+		for key in mappings.keys() {
+			if let Some(chr) = key.chars().next() {
+				let length = max_length.entry(chr).or_insert(0);
+				*length = std::cmp::max(*length, key.chars().count());
+			}
+		}
+
+		CompiledRuleSet {
+			mappings,
+			max_length,
+		}
+	}
+}
+
+/// Rule set compiled for conversion.
+pub struct CompiledRuleSet {
+	mappings: HashMap<String, Rule>,
+	max_length: HashMap<char, usize>,
+}
+
+/// Converts the next text in the input by applying a single rule to the
+/// beginning of the text.
+///
+/// Returns the generated output, the length of the consumed input in
+/// characters, and the input key matched by the rule. Returns [None] if no
+/// rule applies.
+fn convert_next<'a>(
+	input: &'a str,
+	rules: &CompiledRuleSet,
+	last_input: &str,
+) -> Option<(String, usize, &'a str)> {
+	// Keys are first looked up without changing the case to allow for
+	// case-specific rules. If that fails, we fallback to the lowercase key.
+	let first = input.chars().next()?;
+	let length = rules
+		.max_length
+		.get(&first)
+		.or_else(|| {
+			first
+				.to_lowercase()
+				.next()
+				.and_then(|x| rules.max_length.get(&x))
+		})
+		.cloned()
+		.unwrap_or(0);
+
+	// Start with the longest possible keys and work downward.
+	for key_length in (1..=length).rev() {
+		let key_end = byte_offset(input, key_length);
+		let chunk = &input[..key_end];
+		let rule = match rules.mappings.get(chunk) {
+			Some(rule) => rule,
+			None => match rules.mappings.get(&chunk.to_lowercase()) {
+				Some(rule) => rule,
+				None => continue,
+			},
+		};
+
+		let ctx = Context {
+			next_input: &input[key_end..],
+			last_input,
+		};
+
+		let (output, length) = match &rule.func {
+			Some(func) => match func(&ctx) {
+				Some((output, length)) => {
+					let output = if output.is_empty() {
+						rule.out.clone()
+					} else {
+						output
+					};
+					(output, length)
+				}
+				None => continue,
+			},
+			None => (rule.out.clone(), 0),
+		};
+
+		let length = if length > 0 {
+			length
+		} else if rule.len > 0 {
+			rule.len
+		} else {
+			key_length
+		};
+		return Some((output, length, chunk));
+	}
+
+	None
+}
+
+/// Converts the input text using the compiled rules. Text not matching any
+/// rule is output unmodified.
+pub fn convert(input: &str, rules: &CompiledRuleSet) -> String {
+	let mut output = String::with_capacity(input.len());
+	let mut input = input;
+	let mut last = "";
+	while let Some(next) = input.chars().next() {
+		match convert_next(input, rules, last) {
+			Some((text, length, key)) => {
+				output.push_str(&text);
+				last = key;
+				input = &input[byte_offset(input, length)..];
+			}
+			None => {
+				last = &input[..next.len_utf8()];
+				output.push(next);
+				input = &input[next.len_utf8()..];
+			}
+		}
+	}
+	output
+}
+
+/// Byte offset for the given number of characters in the text, limited to the
+/// text length.
+fn byte_offset(text: &str, chars: usize) -> usize {
+	text.char_indices()
+		.nth(chars)
+		.map(|x| x.0)
+		.unwrap_or_else(|| text.len())
+}
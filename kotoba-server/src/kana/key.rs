@@ -0,0 +1,321 @@
+use super::chars::{to_nfc, to_nfd, MARK_SEMI_VOICED, MARK_VOICED};
+use super::{fullwidth_to_ascii, to_hiragana};
+
+/// Converts the input text to a lookup key that can be stored in an index and
+/// later used to lookup a search term.
+///
+/// The objective of this key is to provide a simplified and normalized term
+/// to be used in dictionary lookups as a first-pass coarse filter. Similar
+/// words are mapped to a single key in a way that allows the lookup to handle
+/// common typos and small orthographic variations.
+///
+/// The actual transformation this method makes to the input is as follows:
+///
+/// - Convert romaji and katakana to hiragana and everything else to lowercase.
+/// - Fullwidth letters/digits are converted to their ASCII equivalent.
+/// - Voiced and semi-voiced sound marks are stripped, e.g. ば／ぱ become `は`.
+/// - Small characters are converted to their large variants. The small っ is
+///   completely stripped, as is a つ in places where it could be a small っ.
+/// - Long vowels are normalized to a single vowel.
+///   - This includes stripping `ー` and removing any number of sequential
+///     repeated vowels such as `ええ`, `ぇぇぇ`, or `えぇ`
+///   - Remaining long vowel pairs such as くう, こう, えい are also converted to
+///     a single vowel (e.g. `く`, `こ`, `え`).
+/// - Finally, non-letter/digit characters are stripped from the final result.
+pub fn to_hiragana_key(input: &str) -> String {
+	// Use the decomposed form so that sound marks can be stripped.
+	let text: Vec<char> = to_nfd(&to_hiragana(&input.to_lowercase()))
+		.chars()
+		.collect();
+
+	// This must run before the other steps as they remove the context needed
+	// to avoid stripping a つ for a false positive.
+	let text = strip_tsu(&text);
+
+	// Convert small characters to their big versions. The small っ is stripped
+	// later, so we don't convert it.
+	let text: Vec<char> = text
+		.into_iter()
+		.map(|chr| match chr {
+			'ゃ' => 'や',
+			'ゅ' => 'ゆ',
+			'ょ' => 'よ',
+			'ぁ' => 'あ',
+			'ぃ' => 'い',
+			'ぅ' => 'う',
+			'ぇ' => 'え',
+			'ぉ' => 'お',
+			_ => chr,
+		})
+		.collect();
+
+	let text = collapse_repeated_vowels(&text);
+
+	// Handle `ヴ` before we strip the voiced marks and it becomes ambiguous.
+	let text = collapse_voiced_u(&text);
+
+	// Strip the sound marks and the long mark before handling long vowels.
+	let text: Vec<char> = text
+		.into_iter()
+		.filter(|&chr| chr != MARK_VOICED && chr != MARK_SEMI_VOICED && chr != 'ー')
+		.collect();
+
+	let text: String = collapse_long_vowel_pairs(&text)
+		.into_iter()
+		.filter(|&chr| chr != 'っ')
+		.collect();
+
+	let text: String = fullwidth_to_ascii(&text)
+		.chars()
+		.filter(|&chr| chr.is_alphabetic() || chr.is_ascii_digit())
+		.collect();
+	to_nfc(&text)
+}
+
+/// Strips a つ from places where it could have been mistaken for a small っ,
+/// that is, in the middle of a word and before a consonant or the end of
+/// the word.
+///
+/// A sequence of つ is never stripped, since those are more likely to be
+/// actual words.
+fn strip_tsu(text: &[char]) -> Vec<char> {
+	const CONSONANTS: &str = "かきくけこさしすせそたちてとなにぬねのはひふへほまみむめもらりるれろ";
+
+	let is_word = |chr: char| chr.is_alphabetic() || chr.is_numeric();
+	let keep = |index: usize| {
+		let before = match index {
+			0 => true,
+			_ => {
+				let prev = text[index - 1];
+				let prev_tsu = prev == MARK_VOICED && index > 1 && text[index - 2] == 'つ';
+				!(is_word(prev) || prev == MARK_VOICED || prev == MARK_SEMI_VOICED)
+					|| prev == 'つ' || prev_tsu
+			}
+		};
+		let after = match text.get(index + 1) {
+			None => false,
+			Some(&next) => (is_word(next) || next == MARK_VOICED) && !CONSONANTS.contains(next),
+		};
+		before || after
+	};
+
+	text.iter()
+		.enumerate()
+		.filter(|&(index, &chr)| chr != 'つ' || keep(index))
+		.map(|(_, &chr)| chr)
+		.collect()
+}
+
+/// Replaces a sequence of the same vowel, optionally separated by `ー`, with
+/// a single instance of the vowel.
+fn collapse_repeated_vowels(text: &[char]) -> Vec<char> {
+	let mut output = Vec::with_capacity(text.len());
+	let mut index = 0;
+	while index < text.len() {
+		let chr = text[index];
+		index += 1;
+		output.push(chr);
+		if "あいうえお".contains(chr) {
+			loop {
+				let mut next = index;
+				while text.get(next) == Some(&'ー') {
+					next += 1;
+				}
+				if text.get(next) != Some(&chr) {
+					break;
+				}
+				index = next + 1;
+			}
+		}
+	}
+	output
+}
+
+/// Replaces the sequence `ゔう` with a single `う`.
+fn collapse_voiced_u(text: &[char]) -> Vec<char> {
+	let mut output = Vec::with_capacity(text.len());
+	let mut index = 0;
+	while index < text.len() {
+		output.push(text[index]);
+		let is_match = text[index] == 'う'
+			&& text.get(index + 1) == Some(&MARK_VOICED)
+			&& text.get(index + 2) == Some(&'う')
+			&& text.get(index + 3) != Some(&MARK_VOICED);
+		index += if is_match { 3 } else { 1 };
+	}
+	output
+}
+
+/// Replaces long vowel pairs such as `こう` or `けい` by the first syllable.
+fn collapse_long_vowel_pairs(text: &[char]) -> Vec<char> {
+	const PAIRS: &[(&str, &str)] = &[
+		("かさたなはまやらわ", "あ"),
+		("えきしちにひみり", "い"),
+		("おくすつぬふむゆる", "う"),
+		("けせてねへめれ", "えい"),
+		("こそとのほもよろ", "おう"),
+	];
+
+	let mut output = Vec::with_capacity(text.len());
+	let mut index = 0;
+	while index < text.len() {
+		let chr = text[index];
+		output.push(chr);
+		let is_pair = match text.get(index + 1) {
+			Some(&next) => PAIRS
+				.iter()
+				.any(|(a, b)| a.contains(chr) && b.contains(next)),
+			None => false,
+		};
+		index += if is_pair { 2 } else { 1 };
+	}
+	output
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn check(input: &str, expected: &str) {
+		assert_eq!(to_hiragana_key(input), expected, "input: {}", input);
+	}
+
+	#[test]
+	fn converts_to_hiragana() {
+		check("koto", "こと");
+		check("KOTO", "こと");
+		check("コト", "こと");
+	}
+
+	#[test]
+	fn strips_sound_marks() {
+		check("かが", "かか");
+		check("きぎ", "きき");
+		check("くぐ", "くく");
+		check("けげ", "けけ");
+		check("こご", "ここ");
+		check("さざ", "ささ");
+		check("しじ", "しし");
+		check("すず", "すす");
+		check("せぜ", "せせ");
+		check("そぞ", "そそ");
+		check("ただ", "たた");
+		check("ちぢ", "ちち");
+		check("つづ", "つつ");
+		check("てで", "てて");
+		check("とど", "とと");
+		check("はばぱ", "ははは");
+		check("ひびぴ", "ひひひ");
+		check("ふぶぷ", "ふふふ");
+		check("へべぺ", "へへへ");
+		check("ほぼぽ", "ほほほ");
+		check("ヴヴ", "うう");
+	}
+
+	#[test]
+	fn converts_fullwidth_to_ascii() {
+		check("ＡＢＣ１２３", "abc123");
+	}
+
+	#[test]
+	fn strips_non_word() {
+		check(
+			"㊉（漢字）　〖Ａ／Ｂ・Ｃ〗、１ー２～３(;-;) かか 123",
+			"漢字abc123かか123",
+		);
+	}
+
+	#[test]
+	fn handles_small_chars() {
+		check("ゃゅょかった:ぁぃぅぇぉ", "やゆよかたあいうえお");
+	}
+
+	#[test]
+	fn strips_tsu_where_small_tsu_is_possible() {
+		// We don't want to strip in those cases
+		check("つく", "つく");
+		check("つづく", "つつく");
+		check("つつく", "つつく");
+		check("づつく", "つつく");
+		check("！つつ", "つつ");
+		check("あつあ", "あつあ");
+		check("いつい", "いつい");
+		check("うつう", "うつ"); // this is actually a long vowel pair
+		check("えつえ", "えつえ");
+		check("おつお", "おつお");
+		check("かたつー", "かたつ");
+
+		// We want to strip in those
+		check("かつた", "かた");
+		check("かたつ", "かた");
+		check("かたつ！", "かた");
+
+		// Check all possible combinations
+		let consonants = concat!(
+			"かきくけこさしすせそたちてとなにぬねのはひふへほらりるれろ",
+			"がぎくげござじずぜぞだぢでどばびぶべぼぱぴぷぺぽ",
+		);
+		for chr in consonants.chars() {
+			let res: String = to_nfd(&chr.to_string())
+				.chars()
+				.filter(|&c| c != MARK_VOICED && c != MARK_SEMI_VOICED)
+				.collect();
+			check(&format!("つ{}", chr), &format!("つ{}", res));
+			check(&format!("{}つ", chr), &res);
+			check(&format!("{}つ{}", chr, chr), &format!("{}{}", res, res));
+		}
+	}
+
+	#[test]
+	fn normalizes_long_vowels() {
+		// Make sure we don't strip different vowel sequences
+		check("あいうえお", "あいうえお");
+		check("うお", "うお");
+		check("いえ", "いえ");
+
+		// First check some tricky corner cases
+		check("ぎゃああああ", "きや");
+		check("あーあーあーあ", "あ");
+		check("あーあーあーあー", "あ");
+		check("ヴぅぅぅぅう", "う");
+		check("ヴぉぉぉぉお", "うお");
+		check("せぇ", "せ");
+		check("ぜぇ", "せ");
+		check("ぇえ", "え");
+		check("ええええええい", "え");
+		check("つううううう", "つ");
+
+		// Check all possible combinations of syllables with their vowels.
+		const A: &[&str] = &["あ", "か", "さ", "た", "な", "は", "ま", "や", "ら", "わ"];
+		const I: &[&str] = &["い", "き", "し", "ち", "に", "ひ", "み", "い", "り", "い"];
+		const U: &[&str] = &["う", "く", "す", "つ", "ぬ", "ふ", "む", "ゆ", "る", "う"];
+		const E: &[&str] = &["え", "け", "せ", "て", "ね", "へ", "め", "え", "れ", "え"];
+		const O: &[&str] = &["お", "こ", "そ", "と", "の", "ほ", "も", "よ", "ろ", "お"];
+
+		let check_col = |ls: &[&str], vowel: &str, small: &str| {
+			for pre in ls {
+				for count in 1..=2 {
+					let vowels = vowel.repeat(count);
+					let smalls = small.repeat(count);
+					check(&format!("{}{}", pre, vowels), pre);
+					check(&format!("{}{}", pre, smalls), pre);
+					check(&format!("{}{}{}", pre, vowel, smalls), pre);
+					check(&format!("{}{}{}", pre, vowels, small), pre);
+					check(&format!("{}{}{}", pre, vowels, smalls), pre);
+					check(&format!("{}{}{}", pre, small, vowels), pre);
+					check(&format!("{}{}{}", pre, smalls, vowel), pre);
+					check(&format!("{}{}{}", pre, smalls, vowels), pre);
+				}
+			}
+		};
+
+		check_col(A, "あ", "ぁ");
+		check_col(I, "い", "ぃ");
+		check_col(U, "う", "ぅ");
+		check_col(E, "え", "ぇ");
+		check_col(O, "お", "ぉ");
+
+		check_col(E, "い", "ぃ");
+		check_col(O, "う", "ぅ");
+	}
+}
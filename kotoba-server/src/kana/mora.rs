@@ -0,0 +1,41 @@
+use super::chars::{MARK_SEMI_VOICED, MARK_VOICED};
+
+/// Small kana that combine with the previous character into a single mora.
+/// The small `っ` is not included since it is a mora by itself.
+const SMALL_KANA: &str = "ゃゅょぁぃぅぇぉゎャュョァィゥェォヮ";
+
+/// Splits a kana text into morae.
+///
+/// Small kana such as in `きょ` are combined with the previous character,
+/// while `っ`, `ん` and `ー` are morae by themselves.
+pub fn to_mora(text: &str) -> Vec<String> {
+	let mut output: Vec<String> = Vec::new();
+	for chr in text.chars() {
+		let combine = SMALL_KANA.contains(chr) || chr == MARK_VOICED || chr == MARK_SEMI_VOICED;
+		match output.last_mut() {
+			Some(last) if combine => last.push(chr),
+			_ => output.push(chr.to_string()),
+		}
+	}
+	output
+}
+
+/// Number of morae in a kana text. See [to_mora].
+pub fn count_mora(text: &str) -> usize {
+	to_mora(text).len()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn splits_mora() {
+		assert_eq!(to_mora("きょうと"), vec!["きょ", "う", "と"]);
+		assert_eq!(to_mora("がっこう"), vec!["が", "っ", "こ", "う"]);
+		assert_eq!(to_mora("シャンプー"), vec!["シャ", "ン", "プ", "ー"]);
+		assert_eq!(to_mora("ティッシュ"), vec!["ティ", "ッ", "シュ"]);
+		assert_eq!(to_mora("か\u{3099}"), vec!["か\u{3099}"]);
+		assert_eq!(count_mora(""), 0);
+	}
+}
@@ -0,0 +1,586 @@
+//! Rule sets for the kana conversion.
+//!
+//! This is a port of `lib-ts/kana/kana_rules.ts`. The character tables are
+//! in `(hiragana, katakana, romaji)` form and are mapped into rules according
+//! to the conversion direction.
+
+use super::conversion::{m, m_fn, m_len, RuleSet};
+
+/// Main rule set to convert from any input to hiragana.
+pub fn rules_to_hiragana() -> RuleSet {
+	RuleSet::new()
+		.merge(set_katakana_to_hiragana())
+		.merge(set_romaji_to_kana(true))
+}
+
+/// Rules to convert just the fullwidth characters to ASCII.
+pub fn rules_fullwidth_to_ascii() -> RuleSet {
+	RuleSet::new()
+		.merge(set_punctuation_to_romaji())
+		.add_all(FULLWIDTH_ASCII.iter().map(|&(r, k)| m(k, r)))
+}
+
+/// Rules to convert just ASCII letters and digits to fullwidth.
+pub fn rules_ascii_to_fullwidth() -> RuleSet {
+	RuleSet::new().add_all(FULLWIDTH_ASCII.iter().map(|&(r, k)| m(r, k)))
+}
+
+//============================================================================//
+// Mappers
+//============================================================================//
+
+/// Maps any rule with an input ending in a romaji vowel to also generate the
+/// variants for the accented long vowels. The variants have the same output,
+/// but add a kana long sound mark to it.
+///
+/// This only works with simple (non-function) rules.
+fn map_rules_for_long_vowels(rules: RuleSet) -> RuleSet {
+	const I: &str = "aeiouAEIOU";
+	const A: &str = "âêîôûÂÊÎÔÛ";
+	const B: &str = "āēīōūĀĒĪŌŪ";
+
+	let replace = |key: &str, table: &str| {
+		let mut chars: Vec<char> = key.chars().collect();
+		let last = chars.pop().unwrap();
+		let index = I.chars().position(|c| c == last).unwrap();
+		chars.push(table.chars().nth(index).unwrap());
+		chars.into_iter().collect::<String>()
+	};
+
+	rules.transform(|rule| {
+		let vowel = rule.key.ends_with(|c: char| I.contains(c));
+		if vowel && !rule.out.is_empty() {
+			let out = format!("{}ー", rule.out);
+			vec![
+				rule.clone(),
+				m(replace(&rule.key, A), out.clone()),
+				m(replace(&rule.key, B), out),
+			]
+		} else {
+			vec![rule.clone()]
+		}
+	})
+}
+
+//============================================================================//
+// Rule sets
+//============================================================================//
+
+/// All rules to convert katakana to hiragana.
+fn set_katakana_to_hiragana() -> RuleSet {
+	RuleSet::new()
+		// Basic letters
+		.add_all(KANA.iter().map(|&(h, k, _)| m(k, h)))
+		.add_all(SMALL_KANA.iter().map(|&(h, k, _)| m(k, h)))
+		// Rare and weird characters
+		.add(m("ヽ", "ゝ")) // Iteration Mark
+		.add(m("ヾ", "ゞ")) // Voiced Iteration Mark
+		.add(m("ヿ", "こと")) // Digraph Koto
+		.add(m("ｰ", "ー")) // Halfwidth Katakana-Hiragana Prolonged Sound Mark
+		.add_all(RARE_KATAKANA.iter().map(|&(h, k, _)| m(k, h)))
+}
+
+/// All rules to convert romaji to hiragana or katakana.
+fn set_romaji_to_kana(hiragana: bool) -> RuleSet {
+	let kana = move |h: &str, k: &str| {
+		if hiragana {
+			h.to_string()
+		} else {
+			k.to_string()
+		}
+	};
+	let mapper = move |&(h, k, r): &(&str, &str, &str)| m(r, kana(h, k));
+
+	let n = kana("ん", "ン");
+	let output = RuleSet::new()
+		.add_all(KANA.iter().map(mapper))
+		.add_all(DIGRAPHS.iter().map(mapper)) // after KANA to override archaic characters
+		.add_all(ROMAJI_PUNCTUATION.iter().map(|&(r, k)| m(r, k)))
+		.merge(set_romaji_double_consonants(hiragana))
+		.merge(set_romaji_quoted_long_vowels(hiragana))
+		.add_all(ROMAJI_IME.iter().map(mapper))
+		.add(m_fn("nn", move |ctx| {
+			// At the end of an input, we map 'nn' -> 'ん' so that typing a
+			// double N with IME will generate 'ん'.
+			if ctx.next_input.is_empty() {
+				return Some((n.clone(), 2));
+			}
+			// Otherwise we map 'n' -> 'ん', ignoring the 'nn'. We don't want
+			// to generate a 'っ' here because that is the less useful sequence.
+			Some((n.clone(), 1))
+		}));
+
+	map_rules_for_long_vowels(output)
+}
+
+/// Conversion for the double consonants using っ or ッ.
+fn set_romaji_double_consonants(hiragana: bool) -> RuleSet {
+	// Note that `l` and `x` are not included, and `n` is handled separately.
+	const CONSONANTS: &str = "bcdfghjkmpqrstvwyz";
+	let tsu = if hiragana { "っ" } else { "ッ" };
+	RuleSet::new().add_all(
+		CONSONANTS
+			.chars()
+			.map(|c| m_len(format!("{}{}", c, c), tsu, 1)),
+	)
+}
+
+/// Conversion rules for sequences like "ka'a" to "かあ" or "カア".
+fn set_romaji_quoted_long_vowels(hiragana: bool) -> RuleSet {
+	let vowels = if hiragana {
+		"あいうえお"
+	} else {
+		"アイウエオ"
+	};
+	RuleSet::new().add_all("aiueo".chars().zip(vowels.chars()).map(|(key, out)| {
+		m_fn(format!("'{}", key), move |ctx| {
+			// Only translate a sequence like `a'a`, this is to avoid messing
+			// up random quoted text.
+			let last = ctx
+				.last_input
+				.chars()
+				.last()
+				.map(|c| c.to_ascii_lowercase());
+			if last == Some(key) {
+				Some((out.to_string(), 0))
+			} else {
+				None
+			}
+		})
+	}))
+}
+
+/// Conversion rules for Japanese punctuation to romaji.
+fn set_punctuation_to_romaji() -> RuleSet {
+	RuleSet::new()
+		// Lower precedence than the standard punctuation.
+		.add_all(EXTRA_ROMAJI_PUNCTUATION.iter().map(|&(r, k)| m(k, r)))
+		.add_all(ROMAJI_PUNCTUATION.iter().map(|&(r, k)| m(k, r)))
+}
+
+//============================================================================//
+// Character mappings
+//============================================================================//
+
+/// Small kana letters.
+const SMALL_KANA: &[(&str, &str, &str)] = &[
+	("ぁ", "ァ", "a"),
+	("ぃ", "ィ", "i"),
+	("ぅ", "ゥ", "u"),
+	("ぇ", "ェ", "e"),
+	("ぉ", "ォ", "o"),
+	("っ", "ッ", "tsu"),
+	("ゃ", "ャ", "ya"),
+	("ゅ", "ュ", "yu"),
+	("ょ", "ョ", "yo"),
+];
+
+/// Katakana only characters that are rarely used.
+const RARE_KATAKANA: &[(&str, &str, &str)] = &[
+	// Those are rarely used and don't have a corresponding hiragana, so we
+	// need to use the combining mark.
+	("わ\u{3099}", "ヷ", "va"),
+	("ゐ\u{3099}", "ヸ", "vi"),
+	("ゑ\u{3099}", "ヹ", "ve"),
+	("を\u{3099}", "ヺ", "vo"),
+	// Other rare katakana
+	("え", "𛀀", "e"),
+	("く", "ㇰ", "ku"),  // small ku
+	("し", "ㇱ", "shi"), // small si
+	("す", "ㇲ", "su"),  // small su
+	("と", "ㇳ", "to"),  // small to
+	("ぬ", "ㇴ", "nu"),  // small nu
+	("は", "ㇵ", "ha"),  // small ha
+	("ひ", "ㇶ", "hi"),  // small hi
+	("ふ", "ㇷ", "fu"),  // small hu
+	("へ", "ㇸ", "he"),  // small he
+	("ほ", "ㇹ", "ho"),  // small ho
+	("む", "ㇺ", "mu"),  // small mu
+	("ら", "ㇻ", "ra"),  // small ra
+	("り", "ㇼ", "ri"),  // small ri
+	("る", "ㇽ", "ru"),  // small ru
+	("れ", "ㇾ", "re"),  // small re
+	("ろ", "ㇿ", "ro"),  // small ro
+];
+
+/// All common kana letters, except small digraph letters.
+const KANA: &[(&str, &str, &str)] = &[
+	// Those are small but don't participate in any digraph. We need those
+	// first to be overridden by later rules (e.g. for romaji).
+	("ゎ", "ヮ", "wa"),
+	("ゕ", "ヵ", "ka"),
+	("ゖ", "ヶ", "ka"), // transliterate to romaji as "ka"
+	// "N" mappings
+	("ん", "ン", "n'"), // This first so it has lesser precedence
+	("ん", "ン", "n"),
+	// Non-standard romaji mappings (those must come before so they are
+	// overridden when mapping from kana)
+	("か", "カ", "ca"),
+	("し", "シ", "ci"),
+	("く", "ク", "cu"),
+	("せ", "セ", "ce"),
+	("こ", "コ", "co"),
+	("し", "シ", "si"),
+	("じ", "ジ", "zi"),
+	("ち", "チ", "ti"),
+	("ぢ", "ヂ", "dji"),
+	("ぢ", "ヂ", "dzi"),
+	("つ", "ツ", "tu"),
+	("づ", "ヅ", "dzu"),
+	("ふ", "フ", "hu"),
+	("う", "ウ", "wu"),
+	// Normal syllables
+	("あ", "ア", "a"),
+	("い", "イ", "i"),
+	("う", "ウ", "u"),
+	("え", "エ", "e"),
+	("お", "オ", "o"),
+	("か", "カ", "ka"),
+	("き", "キ", "ki"),
+	("く", "ク", "ku"),
+	("け", "ケ", "ke"),
+	("こ", "コ", "ko"),
+	("が", "ガ", "ga"),
+	("ぎ", "ギ", "gi"),
+	("ぐ", "グ", "gu"),
+	("げ", "ゲ", "ge"),
+	("ご", "ゴ", "go"),
+	("さ", "サ", "sa"),
+	("し", "シ", "shi"),
+	("す", "ス", "su"),
+	("せ", "セ", "se"),
+	("そ", "ソ", "so"),
+	("ざ", "ザ", "za"),
+	("じ", "ジ", "ji"),
+	("ず", "ズ", "zu"),
+	("ぜ", "ゼ", "ze"),
+	("ぞ", "ゾ", "zo"),
+	("た", "タ", "ta"),
+	("ち", "チ", "chi"),
+	("つ", "ツ", "tsu"),
+	("て", "テ", "te"),
+	("と", "ト", "to"),
+	("だ", "ダ", "da"),
+	("ぢ", "ヂ", "di"),
+	("づ", "ヅ", "du"),
+	("で", "デ", "de"),
+	("ど", "ド", "do"),
+	("な", "ナ", "na"),
+	("に", "ニ", "ni"),
+	("ぬ", "ヌ", "nu"),
+	("ね", "ネ", "ne"),
+	("の", "ノ", "no"),
+	("は", "ハ", "ha"),
+	("ひ", "ヒ", "hi"),
+	("ふ", "フ", "fu"),
+	("へ", "ヘ", "he"),
+	("ほ", "ホ", "ho"),
+	("ば", "バ", "ba"),
+	("び", "ビ", "bi"),
+	("ぶ", "ブ", "bu"),
+	("べ", "ベ", "be"),
+	("ぼ", "ボ", "bo"),
+	("ぱ", "パ", "pa"),
+	("ぴ", "ピ", "pi"),
+	("ぷ", "プ", "pu"),
+	("ぺ", "ペ", "pe"),
+	("ぽ", "ポ", "po"),
+	("ま", "マ", "ma"),
+	("み", "ミ", "mi"),
+	("む", "ム", "mu"),
+	("め", "メ", "me"),
+	("も", "モ", "mo"),
+	("や", "ヤ", "ya"),
+	("ゆ", "ユ", "yu"),
+	("よ", "ヨ", "yo"),
+	("ら", "ラ", "ra"),
+	("り", "リ", "ri"),
+	("る", "ル", "ru"),
+	("れ", "レ", "re"),
+	("ろ", "ロ", "ro"),
+	("わ", "ワ", "wa"),
+	("ゐ", "ヰ", "wi"),
+	("ゑ", "ヱ", "we"),
+	("を", "ヲ", "wo"),
+	("ゔ", "ヴ", "vu"),
+];
+
+/// Syllables made from the combination of kana and small letters.
+const DIGRAPHS: &[(&str, &str, &str)] = &[
+	// Non-default combinations first, so they are overridden when generating
+	// romaji.
+	("じゃ", "ジャ", "jya"),
+	("じゅ", "ジュ", "jyu"),
+	("じぇ", "ジェ", "jye"),
+	("じょ", "ジョ", "jyo"),
+	("じゃ", "ジャ", "zya"),
+	("じゅ", "ジュ", "zyu"),
+	("じぇ", "ジェ", "zye"),
+	("じょ", "ジョ", "zyo"),
+	("ちゃ", "チャ", "tya"),
+	("ちゅ", "チュ", "tyu"),
+	("ちぇ", "チェ", "tye"),
+	("ちょ", "チョ", "tyo"),
+	("ぢゃ", "ヂャ", "dja"),
+	("ぢゅ", "ヂュ", "dju"),
+	("ぢぇ", "ヂェ", "dje"),
+	("ぢょ", "ヂョ", "djo"),
+	("ぢゃ", "ヂャ", "dza"),
+	("ぢぇ", "ヂェ", "dze"),
+	("ぢょ", "ヂョ", "dzo"),
+	("くぁ", "クァ", "qwa"),
+	("くぃ", "クィ", "qwi"),
+	("くぇ", "クェ", "qwe"),
+	("くぉ", "クォ", "qwo"),
+	// Default combinations
+	("いぇ", "イェ", "ye"),
+	("きゃ", "キャ", "kya"),
+	("きゅ", "キュ", "kyu"),
+	("きぇ", "キェ", "kye"),
+	("きょ", "キョ", "kyo"),
+	("ぎゃ", "ギャ", "gya"),
+	("ぎゅ", "ギュ", "gyu"),
+	("ぎぇ", "ギェ", "gye"),
+	("ぎょ", "ギョ", "gyo"),
+	("くぁ", "クァ", "qua"),
+	("くぃ", "クィ", "qui"),
+	("くぇ", "クェ", "que"),
+	("くぉ", "クォ", "quo"),
+	("しゃ", "シャ", "sha"),
+	("しゅ", "シュ", "shu"),
+	("しぇ", "シェ", "she"),
+	("しょ", "ショ", "sho"),
+	("じゃ", "ジャ", "ja"),
+	("じゅ", "ジュ", "ju"),
+	("じぇ", "ジェ", "je"),
+	("じょ", "ジョ", "jo"),
+	("ちゃ", "チャ", "cha"),
+	("ちゅ", "チュ", "chu"),
+	("ちぇ", "チェ", "che"),
+	("ちょ", "チョ", "cho"),
+	("ぢゃ", "ヂャ", "dya"),
+	("ぢゅ", "ヂュ", "dyu"),
+	("ぢぇ", "ヂェ", "dye"),
+	("ぢょ", "ヂョ", "dyo"),
+	("にゃ", "ニャ", "nya"),
+	("にゅ", "ニュ", "nyu"),
+	("にぇ", "ニェ", "nye"),
+	("にょ", "ニョ", "nyo"),
+	("ひゃ", "ヒャ", "hya"),
+	("ひゅ", "ヒュ", "hyu"),
+	("ひぇ", "ヒェ", "hye"),
+	("ひょ", "ヒョ", "hyo"),
+	("びゃ", "ビャ", "bya"),
+	("びゅ", "ビュ", "byu"),
+	("びぇ", "ビェ", "bye"),
+	("びょ", "ビョ", "byo"),
+	("ぴゃ", "ピャ", "pya"),
+	("ぴゅ", "ピュ", "pyu"),
+	("ぴぇ", "ピェ", "pye"),
+	("ぴょ", "ピョ", "pyo"),
+	("ふぁ", "ファ", "fa"),
+	("ふぃ", "フィ", "fi"),
+	("ふぇ", "フェ", "fe"),
+	("ふぉ", "フォ", "fo"),
+	("みゃ", "ミャ", "mya"),
+	("みゅ", "ミュ", "myu"),
+	("みぇ", "ミェ", "mye"),
+	("みょ", "ミョ", "myo"),
+	("りゃ", "リャ", "rya"),
+	("りゅ", "リュ", "ryu"),
+	("りぇ", "リェ", "rye"),
+	("りょ", "リョ", "ryo"),
+	// Override the archaic and rare characters with combinations
+	("うぃ", "ウィ", "wi"),
+	("うぇ", "ウェ", "we"),
+	("ゔぁ", "ヴァ", "va"),
+	("ゔぃ", "ヴィ", "vi"),
+	("ゔぇ", "ヴェ", "ve"),
+	("ゔぉ", "ヴォ", "vo"),
+];
+
+/// Extra romaji sequences for IME input only.
+const ROMAJI_IME: &[(&str, &str, &str)] = &[
+	("ぁ", "ァ", "xa"),
+	("ぃ", "ィ", "xi"),
+	("ぅ", "ゥ", "xu"),
+	("ぇ", "ェ", "xe"),
+	("ぉ", "ォ", "xo"),
+	("ゐ", "ヰ", "xwi"),
+	("ゑ", "ヱ", "xwe"),
+	("わ\u{3099}", "ヷ", "xva"),
+	("ゐ\u{3099}", "ヸ", "xvi"),
+	("ゑ\u{3099}", "ヹ", "xve"),
+	("を\u{3099}", "ヺ", "xvo"),
+	("ゃ", "ャ", "xya"),
+	("ゅ", "ュ", "xyu"),
+	("ょ", "ョ", "xyo"),
+	("っ", "ッ", "xtu"),
+	("っ", "ッ", "xtsu"),
+	("どぅ", "ドゥ", "xdu"),
+	("てぃ", "ティ", "xti"),
+	("でぃ", "ディ", "xdi"),
+	("ゎ", "ヮ", "xwa"),
+	("ゕ", "ヵ", "xka"),
+	("ゖ", "ヶ", "xke"),
+];
+
+/// The standard romaji punctuation in `(romaji, kana)` form.
+const ROMAJI_PUNCTUATION: &[(&str, &str)] = &[
+	(" ", "\u{3000}"),
+	("/", "・"), // Katakana Middle Dot
+	(",", "、"), // Ideographic Comma
+	(".", "。"), // Ideographic Full Stop
+	("[", "「"), // Left Corner Bracket
+	("]", "」"), // Right Corner Bracket
+	("«", "《"), // Left Double Angle Bracket
+	("»", "》"), // Right Double Angle Bracket
+	("!", "！"),
+	("\"", "＂"),
+	("#", "＃"),
+	("$", "＄"),
+	("%", "％"),
+	("&", "＆"),
+	("'", "＇"),
+	("(", "（"),
+	(")", "）"),
+	("*", "＊"),
+	("+", "＋"),
+	(":", "："),
+	(";", "；"),
+	("<", "＜"),
+	("=", "＝"),
+	(">", "＞"),
+	("?", "？"),
+	("@", "＠"),
+	("\\", "＼"),
+	("^", "＾"),
+	("_", "＿"),
+	("`", "｀"),
+	("{", "｛"),
+	("|", "｜"),
+	("}", "｝"),
+	("~", "～"),
+	// Monetary symbols
+	("¢", "￠"),
+	("£", "￡"),
+	("¬", "￢"),
+	("¯", "￣"),
+	("¥", "￥"),
+	("₩", "￦"),
+	// Override the '-' generation from romaji to kana
+	("-", "ー"),
+];
+
+/// Lower precedence symbols that are never generated from romaji, but need to
+/// be mapped to romaji as well.
+const EXTRA_ROMAJI_PUNCTUATION: &[(&str, &str)] = &[
+	("-", "ｰ"),   // Halfwidth prolonged sound mark
+	("=", "゠"),  // Katakana-Hiragana Double Hyphen
+	("<", "〈"),  // Left Angle Bracket
+	(">", "〉"),  // Right Angle Bracket
+	("[", "『"),  // Left White Corner Bracket
+	("]", "』"),  // Right White Corner Bracket
+	("[", "【"),  // Left Black Lenticular Bracket
+	("]", "】"),  // Right Black Lenticular Bracket
+	("{", "〔"),  // Left Tortoise Shell Bracket
+	("}", "〕"),  // Right Tortoise Shell Bracket
+	("[", "〖"),  // Left White Lenticular Bracket
+	("]", "〗"),  // Right White Lenticular Bracket
+	("{", "〘"),  // Left White Tortoise Shell Bracket
+	("}", "〙"),  // Right White Tortoise Shell Bracket
+	("[", "〚"),  // Left White Square Bracket
+	("]", "〛"),  // Right White Square Bracket
+	("~", "〜"),  // Wave Dash
+	("\"", "〝"), // Reversed Double Prime Quotation Mark
+	("\"", "〞"), // Double Prime Quotation Mark
+	("\"", "〟"), // Low Double Prime Quotation Mark
+	// Fullwidth and halfwidth symbols
+	(",", "，"),
+	("-", "－"),
+	(".", "．"),
+	("/", "／"),
+	("[", "［"),
+	("]", "］"),
+	("(", "｟"),
+	(")", "｠"),
+	("|", "￤"),
+	(".", "｡"),
+	("[", "｢"),
+	("]", "｣"),
+	(",", "､"),
+	("/", "･"),
+	("|", "￨"),
+	("←", "￩"),
+	("↑", "￪"),
+	("→", "￫"),
+	("↓", "￬"),
+	("■", "￭"),
+	("○", "￮"),
+];
+
+/// Fullwidth ASCII letters and digits in `(ascii, fullwidth)` form.
+const FULLWIDTH_ASCII: &[(&str, &str)] = &[
+	("0", "０"),
+	("1", "１"),
+	("2", "２"),
+	("3", "３"),
+	("4", "４"),
+	("5", "５"),
+	("6", "６"),
+	("7", "７"),
+	("8", "８"),
+	("9", "９"),
+	("A", "Ａ"),
+	("B", "Ｂ"),
+	("C", "Ｃ"),
+	("D", "Ｄ"),
+	("E", "Ｅ"),
+	("F", "Ｆ"),
+	("G", "Ｇ"),
+	("H", "Ｈ"),
+	("I", "Ｉ"),
+	("J", "Ｊ"),
+	("K", "Ｋ"),
+	("L", "Ｌ"),
+	("M", "Ｍ"),
+	("N", "Ｎ"),
+	("O", "Ｏ"),
+	("P", "Ｐ"),
+	("Q", "Ｑ"),
+	("R", "Ｒ"),
+	("S", "Ｓ"),
+	("T", "Ｔ"),
+	("U", "Ｕ"),
+	("V", "Ｖ"),
+	("W", "Ｗ"),
+	("X", "Ｘ"),
+	("Y", "Ｙ"),
+	("Z", "Ｚ"),
+	("a", "ａ"),
+	("b", "ｂ"),
+	("c", "ｃ"),
+	("d", "ｄ"),
+	("e", "ｅ"),
+	("f", "ｆ"),
+	("g", "ｇ"),
+	("h", "ｈ"),
+	("i", "ｉ"),
+	("j", "ｊ"),
+	("k", "ｋ"),
+	("l", "ｌ"),
+	("m", "ｍ"),
+	("n", "ｎ"),
+	("o", "ｏ"),
+	("p", "ｐ"),
+	("q", "ｑ"),
+	("r", "ｒ"),
+	("s", "ｓ"),
+	("t", "ｔ"),
+	("u", "ｕ"),
+	("v", "ｖ"),
+	("w", "ｗ"),
+	("x", "ｘ"),
+	("y", "ｙ"),
+	("z", "ｚ"),
+];
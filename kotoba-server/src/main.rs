@@ -1,6 +1,3 @@
-// For rocket:
-#![feature(proc_macro_hygiene)]
-#![feature(decl_macro)]
 // TODO: remove this
 #![allow(dead_code)]
 #![allow(unused_imports)]
@@ -18,8 +15,16 @@ extern crate actix_web;
 extern crate juniper;
 
 mod app;
+mod dict;
+mod files;
 mod graph;
 mod graphql;
+mod history;
+mod inflection;
+mod kana;
+mod pitch;
+mod search;
+mod segment;
 mod server;
 
 #[actix_web::main]
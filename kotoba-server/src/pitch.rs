@@ -0,0 +1,160 @@
+use actix_web as web;
+
+use crate::app::App;
+use crate::kana;
+
+/// Horizontal distance between the mora in the diagram.
+const STEP_X: usize = 32;
+
+/// Vertical position of the high and low pitch dots.
+const HIGH_Y: usize = 12;
+const LOW_Y: usize = 36;
+
+/// Vertical position of the mora text baseline.
+const TEXT_Y: usize = 66;
+
+/// Height of the diagram for a single pitch.
+const HEIGHT: usize = 76;
+
+const DOT_RADIUS: usize = 5;
+
+const COLOR: &str = "#222";
+
+/// Renders the pitch accent diagrams for a reading of an entry as an SVG
+/// image, with one diagram for each pitch of the reading.
+///
+/// The entry is given by its sequence number and the reading must match one
+/// of the entry readings exactly.
+#[get("/pitch/{entry}/{reading}.svg")]
+pub async fn svg(
+	app: web::web::Data<&'static App>,
+	path: web::web::Path<(String, String)>,
+) -> web::HttpResponse {
+	let (sequence, reading) = path.into_inner();
+	let entry = app.dict().get(&sequence);
+	let reading = entry.and_then(|entry| entry.reading.iter().find(|x| x.expr == reading));
+	let reading = match reading {
+		Some(reading) if !reading.pitches.is_empty() => reading,
+		_ => return web::HttpResponse::NotFound().body("pitch not found"),
+	};
+
+	let morae = kana::to_mora(&reading.expr);
+	let values: Vec<usize> = reading.pitches.iter().map(|x| x.value).collect();
+	web::HttpResponse::Ok()
+		.content_type("image/svg+xml")
+		.body(render(&morae, &values))
+}
+
+/// Renders the pitch diagrams for the morae, one below the other.
+fn render(morae: &[String], values: &[usize]) -> String {
+	// The extra column is for the following particle.
+	let width = STEP_X * (morae.len() + 1);
+	let height = HEIGHT * values.len();
+	let mut output = format!(
+		r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+		w = width,
+		h = height,
+	);
+	for (index, &value) in values.iter().enumerate() {
+		output.push_str(&render_pitch(morae, value, HEIGHT * index));
+	}
+	output.push_str("</svg>");
+	output
+}
+
+/// Renders a single pitch diagram with the dots, the connecting lines, and
+/// the morae below them.
+fn render_pitch(morae: &[String], value: usize, offset: usize) -> String {
+	let pattern = pitch_pattern(morae.len(), value);
+	let points: Vec<(usize, usize)> = pattern
+		.iter()
+		.enumerate()
+		.map(|(index, &high)| {
+			let x = STEP_X * index + STEP_X / 2;
+			let y = offset + if high { HIGH_Y } else { LOW_Y };
+			(x, y)
+		})
+		.collect();
+
+	let mut output = String::from("<g>");
+	let path: Vec<String> = points.iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+	output.push_str(&format!(
+		r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"/>"#,
+		path.join(" "),
+		COLOR
+	));
+	for (index, (x, y)) in points.iter().enumerate() {
+		// The dot for the particle is hollow.
+		let fill = if index < morae.len() { COLOR } else { "#fff" };
+		output.push_str(&format!(
+			r#"<circle cx="{}" cy="{}" r="{}" fill="{}" stroke="{}" stroke-width="2"/>"#,
+			x, y, DOT_RADIUS, fill, COLOR
+		));
+	}
+	for ((x, _), mora) in points.iter().zip(morae) {
+		let size = if mora.chars().count() > 1 { 12 } else { 16 };
+		output.push_str(&format!(
+			r#"<text x="{}" y="{}" font-size="{}" text-anchor="middle" fill="{}">{}</text>"#,
+			x,
+			offset + TEXT_Y,
+			size,
+			COLOR,
+			escape(mora)
+		));
+	}
+	output.push_str("</g>");
+	output
+}
+
+/// Computes whether each mora is high for the pitch value, with an extra
+/// final element for the particle following the word.
+///
+/// For a value of 0 (heiban) the first mora is low and the rest are high,
+/// including the particle. For 1 (atamadaka) only the first mora is high.
+/// Otherwise the pitch rises after the first mora and drops after mora N.
+fn pitch_pattern(count: usize, value: usize) -> Vec<bool> {
+	(0..=count)
+		.map(|index| match value {
+			0 => index > 0,
+			1 => index == 0,
+			_ => index > 0 && index < value,
+		})
+		.collect()
+}
+
+fn escape(text: &str) -> String {
+	text.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn pattern(count: usize, value: usize) -> String {
+		let list = pitch_pattern(count, value);
+		list.iter().map(|&x| if x { 'H' } else { 'L' }).collect()
+	}
+
+	#[test]
+	fn computes_pitch_pattern() {
+		assert_eq!(pattern(4, 0), "LHHHH");
+		assert_eq!(pattern(4, 1), "HLLLL");
+		assert_eq!(pattern(4, 2), "LHLLL");
+		assert_eq!(pattern(4, 4), "LHHHL");
+		assert_eq!(pattern(1, 0), "LH");
+		assert_eq!(pattern(1, 1), "HL");
+	}
+
+	#[test]
+	fn renders_svg() {
+		let morae = kana::to_mora("きょう");
+		let image = render(&morae, &[1, 0]);
+		assert!(image.starts_with("<svg"));
+		assert!(image.ends_with("</svg>"));
+		assert_eq!(image.matches("<g>").count(), 2);
+		assert_eq!(image.matches("<circle").count(), 6);
+		assert!(image.contains(">きょ</text>"));
+	}
+}
@@ -0,0 +1,13 @@
+//! Dictionary search.
+
+mod cache;
+mod engine;
+mod index;
+mod query;
+mod task;
+
+pub use cache::*;
+pub use engine::*;
+pub use index::*;
+pub use query::*;
+pub use task::*;
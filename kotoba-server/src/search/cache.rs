@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::query::Expr;
+use super::task::Search;
+
+/// Maximum number of searches kept in the cache. This is a soft limit since
+/// recently used searches are never removed.
+const MAX_CACHE_ENTRIES: usize = 100;
+
+/// Minimum time a search is kept in the cache after it was last used.
+const MIN_ENTRY_TTL: Duration = Duration::from_secs(2 * 60);
+
+/// Cache of running and completed searches, keyed by the normalized query.
+///
+/// Incremental searches will repeat the same queries as the user types, so
+/// this avoids running them again.
+#[derive(Default)]
+pub struct Cache {
+	entries: Mutex<HashMap<String, CacheEntry>>,
+}
+
+struct CacheEntry {
+	search: Arc<Search>,
+	history: bool,
+	last_used: Instant,
+}
+
+impl Cache {
+	/// Returns the cached search for the expression. If there is none, this
+	/// calls `start` to start a new search and caches it.
+	pub fn get<F: FnOnce() -> Arc<Search>>(&self, expr: &Expr, start: F) -> Arc<Search> {
+		let key = expr.to_string().to_uppercase();
+		let mut entries = self.entries.lock().unwrap();
+		if let Some(entry) = entries.get_mut(&key) {
+			entry.last_used = Instant::now();
+			return entry.search.clone();
+		}
+
+		clean_up(&mut entries);
+		let search = start();
+		entries.insert(
+			key,
+			CacheEntry {
+				search: search.clone(),
+				history: expr.uses_history(),
+				last_used: Instant::now(),
+			},
+		);
+		search
+	}
+
+	/// Removes all searches that reference the word history. This must be
+	/// called when the history changes.
+	pub fn remove_history(&self) {
+		let mut entries = self.entries.lock().unwrap();
+		entries.retain(|_, entry| !entry.history);
+	}
+}
+
+/// Removes the least recently used entries if the cache is over the limit,
+/// as long as they are past the minimum TTL.
+fn clean_up(entries: &mut HashMap<String, CacheEntry>) {
+	if entries.len() < MAX_CACHE_ENTRIES {
+		return;
+	}
+
+	let mut candidates: Vec<(Instant, String)> = entries
+		.iter()
+		.filter(|(_, entry)| entry.last_used.elapsed() >= MIN_ENTRY_TTL)
+		.map(|(key, entry)| (entry.last_used, key.clone()))
+		.collect();
+	candidates.sort();
+
+	let count = (entries.len() + 1 - MAX_CACHE_ENTRIES).min(candidates.len());
+	for (_, key) in candidates.into_iter().take(count) {
+		entries.remove(&key);
+	}
+}
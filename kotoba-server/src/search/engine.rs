@@ -0,0 +1,666 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::dict::Dict;
+use crate::kana;
+
+use super::index::{approx_key, exact_key, Index, IndexRow};
+use super::query::{Expr, Keyword, KeywordMode, GLOB_ANY, GLOB_ONE, HISTORY};
+
+/// Matching group for a search result. Results are sorted by group first.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Tier {
+	Exact,
+	Approx,
+	Fuzzy,
+}
+
+impl Tier {
+	/// All tiers in order of relevance.
+	pub const ALL: [Tier; 3] = [Tier::Exact, Tier::Approx, Tier::Fuzzy];
+}
+
+/// How the keyword matched the text. Inside a [Tier], results are sorted by
+/// the kind of match.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Kind {
+	Full,
+	Prefix,
+	Suffix,
+	Contains,
+}
+
+/// Result of a search matching a dictionary entry.
+#[derive(Clone, Debug)]
+pub struct Match {
+	/// Index of the entry in the dictionary.
+	pub entry: usize,
+
+	pub tier: Tier,
+	pub kind: Kind,
+
+	/// Keyword that matched the entry.
+	pub query: String,
+
+	/// Full text of the kanji or reading element that was matched, converted
+	/// to hiragana.
+	pub text: String,
+
+	/// Sequence (possibly non-continuous) of the characters of `text` that
+	/// were matched.
+	pub segments: String,
+
+	/// Penalty for a fuzzy match based on the edit distance and gaps between
+	/// the matched characters. This is zero for other tiers.
+	pub penalty: usize,
+}
+
+impl Match {
+	/// Match mode as exposed by the `EntryMatch` type (e.g. `approx-prefix`).
+	pub fn mode(&self) -> String {
+		let tier = match self.tier {
+			Tier::Exact => "",
+			Tier::Approx => "approx",
+			Tier::Fuzzy => "fuzzy",
+		};
+		let kind = match self.kind {
+			Kind::Full => "",
+			Kind::Prefix => "prefix",
+			Kind::Suffix => "suffix",
+			Kind::Contains => "contains",
+		};
+		match (tier, kind) {
+			("", "") => String::from("exact"),
+			("", kind) => kind.to_string(),
+			(tier, "") => tier.to_string(),
+			(tier, kind) => format!("{}-{}", tier, kind),
+		}
+	}
+
+	/// Relevance of the match. Lower values are better.
+	///
+	/// Inside a tier, matches are sorted by kind first, so the penalty only
+	/// orders fuzzy matches of the same kind.
+	fn rank(&self) -> (Tier, Kind, usize, usize) {
+		(
+			self.tier,
+			self.kind,
+			self.penalty,
+			self.text.chars().count(),
+		)
+	}
+}
+
+/// Evaluates search queries against the [Index].
+#[derive(Clone)]
+pub struct Engine<'a> {
+	dict: &'a Dict,
+	index: &'a Index,
+	history: Arc<HashSet<usize>>,
+}
+
+impl<'a> Engine<'a> {
+	/// Creates a new engine. The `history` is the set of entries matched by
+	/// [Expr::History].
+	pub fn new(dict: &'a Dict, index: &'a Index, history: Arc<HashSet<usize>>) -> Engine<'a> {
+		Engine {
+			dict,
+			index,
+			history,
+		}
+	}
+
+	/// Returns all matches for the expression, sorted by relevance.
+	///
+	/// Entries are only returned for the best tier they match.
+	pub fn search(&self, expr: &Expr) -> Vec<Match> {
+		let mut result: Vec<Match> = self.eval(expr, false).into_values().collect();
+		result.sort_by(|a, b| self.compare(a, b));
+		result
+	}
+
+	/// Sorts by the kind of match and length, then by popularity and frequency
+	/// of the entry.
+	fn compare(&self, a: &Match, b: &Match) -> Ordering {
+		let entries = self.dict.entries();
+		let (ea, eb) = (&entries[a.entry], &entries[b.entry]);
+		a.rank()
+			.cmp(&b.rank())
+			.then_with(|| eb.popular().cmp(&ea.popular()))
+			.then_with(|| {
+				let fa = ea.frequency.unwrap_or(0.0);
+				let fb = eb.frequency.unwrap_or(0.0);
+				fb.partial_cmp(&fa).unwrap_or(Ordering::Equal)
+			})
+			.then_with(|| ea.position.cmp(&eb.position))
+	}
+
+	/// Evaluates an expression returning the best match for each entry.
+	///
+	/// If `strict` is true, only full exact matches are considered. This is
+	/// used for negated expressions.
+	fn eval(&self, expr: &Expr, strict: bool) -> HashMap<usize, Match> {
+		match expr {
+			Expr::Keyword(keyword) => self.eval_keyword(keyword, strict),
+			Expr::Or(items) => {
+				let mut output: HashMap<usize, Match> = HashMap::new();
+				let mut negated = HashSet::new();
+				for it in items {
+					if let Expr::Not(inner) = it {
+						negated.extend(self.eval(inner, true).into_keys());
+						continue;
+					}
+					for m in self.eval(it, strict).into_values() {
+						insert_best(&mut output, m);
+					}
+				}
+				output.retain(|entry, _| !negated.contains(entry));
+				output
+			}
+			Expr::And(items) => {
+				let mut output: Option<HashMap<usize, Match>> = None;
+				let mut negated = HashSet::new();
+				for it in items {
+					if let Expr::Not(inner) = it {
+						negated.extend(self.eval(inner, true).into_keys());
+						continue;
+					}
+					let mut matches = self.eval(it, strict);
+					output = Some(match output {
+						None => matches,
+						Some(mut output) => {
+							// All terms must match, so the entry is only as
+							// relevant as its worst match.
+							output.retain(|entry, _| matches.contains_key(entry));
+							for (entry, cur) in output.iter_mut() {
+								let m = matches.remove(entry).unwrap();
+								if m.rank() > cur.rank() {
+									*cur = m;
+								}
+							}
+							output
+						}
+					});
+				}
+				let mut output = output.unwrap_or_default();
+				output.retain(|entry, _| !negated.contains(entry));
+				output
+			}
+			// Fully negative expressions are too broad, so they only work to
+			// filter other expressions.
+			Expr::Not(_) => HashMap::new(),
+			Expr::History => self.eval_history(),
+		}
+	}
+
+	fn eval_history(&self) -> HashMap<usize, Match> {
+		let entries = self.dict.entries();
+		let mut output = HashMap::new();
+		for &entry in self.history.iter() {
+			let text = kana::to_hiragana(entries[entry].word());
+			let m = Match {
+				entry,
+				tier: Tier::Exact,
+				kind: Kind::Full,
+				query: HISTORY.to_string(),
+				segments: text.clone(),
+				text,
+				penalty: 0,
+			};
+			output.insert(entry, m);
+		}
+		output
+	}
+
+	fn eval_keyword(&self, keyword: &Keyword, strict: bool) -> HashMap<usize, Match> {
+		let max_tier = match keyword.mode {
+			_ if strict => Tier::Exact,
+			KeywordMode::Exact => Tier::Exact,
+			KeywordMode::Default => Tier::Approx,
+			KeywordMode::Fuzzy => Tier::Fuzzy,
+		};
+
+		let glob = keyword.has_glob();
+		let exact = map_glob(&keyword.text, exact_key);
+		let approx = map_glob(&keyword.text, approx_key);
+
+		// Full matches are looked up in the index, keeping the best tier for
+		// each row. Glob keys can only be matched by the scan.
+		let mut full: HashMap<usize, Tier> = HashMap::new();
+		if !glob {
+			let lookup = [
+				(Tier::Approx, &approx, self.index.find_approx(&approx)),
+				(Tier::Exact, &exact, self.index.find_exact(&exact)),
+			];
+			for (tier, key, rows) in lookup.iter() {
+				if *tier <= max_tier && !key.is_empty() {
+					full.extend(rows.iter().map(|&row| (row, *tier)));
+				}
+			}
+		}
+
+		let rows = self.index.rows();
+		let mut output: HashMap<usize, Match> = HashMap::new();
+		for (&row, &tier) in full.iter() {
+			let row = &rows[row];
+			let key = if tier == Tier::Exact {
+				&row.exact
+			} else {
+				&row.approx
+			};
+			let positions: Vec<usize> = (0..key.len()).collect();
+			let m = row_match(row, keyword, tier, Kind::Full, 0, &positions);
+			insert_best(&mut output, m);
+		}
+
+		// Strict keywords only match in full, so there is no need to scan the
+		// rows for the other kinds of match.
+		if strict && !glob {
+			return output;
+		}
+
+		let exact: Vec<char> = exact.chars().collect();
+		let approx: Vec<char> = approx.chars().collect();
+		for (index, row) in rows.iter().enumerate() {
+			let full = full.get(&index).cloned();
+			let (tier, kind, penalty, positions) =
+				match match_row(row, &exact, &approx, glob, max_tier, full) {
+					Some(result) => result,
+					None => continue,
+				};
+			if strict && kind != Kind::Full {
+				continue;
+			}
+			let m = row_match(row, keyword, tier, kind, penalty, &positions);
+			insert_best(&mut output, m);
+		}
+		output
+	}
+}
+
+/// Inserts the match in the output if it is the best for its entry.
+fn insert_best(output: &mut HashMap<usize, Match>, m: Match) {
+	match output.get(&m.entry) {
+		Some(cur) if cur.rank() <= m.rank() => {}
+		_ => {
+			output.insert(m.entry, m);
+		}
+	}
+}
+
+/// Creates the match for a row given the matched positions of its key.
+fn row_match(
+	row: &IndexRow,
+	keyword: &Keyword,
+	tier: Tier,
+	kind: Kind,
+	penalty: usize,
+	positions: &[usize],
+) -> Match {
+	let key = if tier == Tier::Exact {
+		exact_key
+	} else {
+		approx_key
+	};
+	let (text, segments) = match_segments(&row.text, key, positions);
+	Match {
+		entry: row.entry,
+		tier,
+		kind,
+		query: keyword.text.clone(),
+		text,
+		segments,
+		penalty,
+	}
+}
+
+/// Matches the keyword against a row trying each tier in order. Returns the
+/// positions of the row key that were matched.
+///
+/// The `full` tier is the tier the row was found in full by the index
+/// lookup. Since that is the best match for the row from that tier on, the
+/// row is skipped once it is reached.
+fn match_row(
+	row: &IndexRow,
+	exact: &[char],
+	approx: &[char],
+	glob: bool,
+	max_tier: Tier,
+	full: Option<Tier>,
+) -> Option<(Tier, Kind, usize, Vec<usize>)> {
+	for &tier in Tier::ALL.iter().filter(|&&x| x <= max_tier) {
+		if full == Some(tier) {
+			return None;
+		}
+		let result = match tier {
+			Tier::Exact => match_text(exact, &row.exact, glob).map(|(k, s)| (k, 0, s)),
+			Tier::Approx => match_text(approx, &row.approx, glob).map(|(k, s)| (k, 0, s)),
+			Tier::Fuzzy if !glob => match_fuzzy(approx, &row.approx),
+			Tier::Fuzzy => None,
+		};
+		if let Some((kind, penalty, positions)) = result {
+			return Some((tier, kind, penalty, positions));
+		}
+	}
+	None
+}
+
+/// Converts the text to hiragana and returns it with the characters that
+/// generated the matched positions of its key.
+///
+/// Since the key may add, drop or merge characters, each character of the
+/// text is mapped to the range of the key generated by it, by computing the
+/// key for each prefix of the text.
+fn match_segments(text: &str, key: fn(&str) -> String, positions: &[usize]) -> (String, String) {
+	let hiragana = kana::to_hiragana(text);
+	let chars: Vec<char> = hiragana.chars().collect();
+	let mut segments = String::new();
+	let mut start = 0;
+	for index in 0..chars.len() {
+		let prefix: String = chars[..=index].iter().collect();
+		let end = std::cmp::max(key(&prefix).chars().count(), start);
+		if positions.iter().any(|&pos| pos >= start && pos < end) {
+			segments.push(chars[index]);
+		}
+		start = end;
+	}
+	(hiragana, segments)
+}
+
+/// Matches the key as a full, prefix, suffix or contained text, returning the
+/// matched positions of the text. Glob keys are always matched against the
+/// full text.
+fn match_text(key: &[char], text: &[char], glob: bool) -> Option<(Kind, Vec<usize>)> {
+	if key.is_empty() || text.is_empty() {
+		return None;
+	}
+
+	if glob {
+		return if match_glob(key, text) {
+			Some((Kind::Full, (0..text.len()).collect()))
+		} else {
+			None
+		};
+	}
+
+	let (kind, start) = if key == text {
+		(Kind::Full, 0)
+	} else if text.starts_with(key) {
+		(Kind::Prefix, 0)
+	} else if text.ends_with(key) {
+		(Kind::Suffix, text.len() - key.len())
+	} else if let Some(start) = text.windows(key.len()).position(|x| x == key) {
+		(Kind::Contains, start)
+	} else {
+		return None;
+	};
+	Some((kind, (start..start + key.len()).collect()))
+}
+
+/// Penalty for each gap between the matched characters in a fuzzy match.
+const FUZZY_GAP_PENALTY: usize = 2;
+
+/// Fuzzy matches with a penalty above this multiple of the key length are
+/// culled from the results.
+const FUZZY_MAX_PENALTY: usize = 2;
+
+/// Matches the characters of the key in order anywhere in the text.
+///
+/// The penalty for the match is the edit distance between the key and the
+/// text plus [FUZZY_GAP_PENALTY] for each gap in the alignment of the key
+/// with the fewest gaps. Matches with a penalty above [FUZZY_MAX_PENALTY]
+/// times the key length are culled.
+///
+/// Returns the positions of the text in the alignment.
+fn match_fuzzy(key: &[char], text: &[char]) -> Option<(Kind, usize, Vec<usize>)> {
+	if key.is_empty() || key.len() > text.len() {
+		return None;
+	}
+
+	// Since the key must be a subsequence of the text, the edit distance is
+	// just the number of characters not matched.
+	let distance = text.len() - key.len();
+	let max_penalty = key.len() * FUZZY_MAX_PENALTY;
+	if distance > max_penalty {
+		return None;
+	}
+
+	let (gaps, positions) = fuzzy_alignment(key, text)?;
+	let penalty = distance + gaps * FUZZY_GAP_PENALTY;
+	if penalty > max_penalty {
+		return None;
+	}
+
+	let prefix = key[0] == text[0];
+	let suffix = key[key.len() - 1] == text[text.len() - 1];
+	let kind = match (prefix, suffix) {
+		(true, true) => Kind::Full,
+		(true, false) => Kind::Prefix,
+		(false, true) => Kind::Suffix,
+		(false, false) => Kind::Contains,
+	};
+	Some((kind, penalty, positions))
+}
+
+/// Returns the alignment of the key in the text with the fewest gaps between
+/// matched characters, as the number of gaps and the matched positions, or
+/// [None] if the key is not a subsequence of the text.
+fn fuzzy_alignment(key: &[char], text: &[char]) -> Option<(usize, Vec<usize>)> {
+	// For each key character, the minimum gaps for the key prefix given the
+	// character is matched at each position of the text, along with the
+	// position matched by the previous character.
+	let first: Vec<Option<(usize, usize)>> = text
+		.iter()
+		.enumerate()
+		.map(|(pos, &chr)| if chr == key[0] { Some((0, pos)) } else { None })
+		.collect();
+	let mut steps = vec![first];
+	for &chr in &key[1..] {
+		let gaps = &steps[steps.len() - 1];
+		let mut next = vec![None; text.len()];
+		// Best alignment for the previous characters ending before `pos - 1`.
+		let mut skipped: Option<(usize, usize)> = None;
+		for pos in 1..text.len() {
+			if text[pos] == chr {
+				let options = [
+					gaps[pos - 1].map(|x| (x.0, pos - 1)),
+					skipped.map(|x| (x.0 + 1, x.1)),
+				];
+				next[pos] = options.iter().flatten().min_by_key(|x| x.0).cloned();
+			}
+			if let Some((count, _)) = gaps[pos - 1] {
+				match skipped {
+					Some(best) if best.0 <= count => {}
+					_ => skipped = Some((count, pos - 1)),
+				}
+			}
+		}
+		steps.push(next);
+	}
+
+	let last = &steps[steps.len() - 1];
+	let (mut pos, gaps) = last
+		.iter()
+		.enumerate()
+		.filter_map(|(pos, x)| x.map(|x| (pos, x.0)))
+		.min_by_key(|x| x.1)?;
+	let mut positions = vec![0; key.len()];
+	for index in (0..key.len()).rev() {
+		positions[index] = pos;
+		pos = steps[index][pos].map(|x| x.1).unwrap_or_default();
+	}
+	Some((gaps, positions))
+}
+
+/// Matches the text with a pattern containing glob operators.
+fn match_glob(pattern: &[char], text: &[char]) -> bool {
+	let (mut p, mut t) = (0, 0);
+	let mut backtrack: Option<(usize, usize)> = None;
+	while t < text.len() {
+		if p < pattern.len() && pattern[p] == GLOB_ANY {
+			backtrack = Some((p, t));
+			p += 1;
+		} else if p < pattern.len() && (pattern[p] == GLOB_ONE || pattern[p] == text[t]) {
+			p += 1;
+			t += 1;
+		} else if let Some((bp, bt)) = backtrack {
+			p = bp + 1;
+			t = bt + 1;
+			backtrack = Some((bp, bt + 1));
+		} else {
+			return false;
+		}
+	}
+	pattern[p..].iter().all(|&c| c == GLOB_ANY)
+}
+
+/// Applies the key function to the text preserving any glob operators.
+fn map_glob<F: Fn(&str) -> String>(text: &str, key: F) -> String {
+	let mut output = String::new();
+	let mut part = String::new();
+	for chr in text.chars() {
+		if chr == GLOB_ANY || chr == GLOB_ONE {
+			output.push_str(&key(&part));
+			output.push(chr);
+			part.clear();
+		} else {
+			part.push(chr);
+		}
+	}
+	output.push_str(&key(&part));
+	output
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn chars(text: &str) -> Vec<char> {
+		text.chars().collect()
+	}
+
+	fn fuzzy_match(key: &str, text: &str) -> Match {
+		let (kind, penalty, _) = match_fuzzy(&chars(key), &chars(text)).unwrap();
+		Match {
+			entry: 0,
+			tier: Tier::Fuzzy,
+			kind,
+			query: key.to_string(),
+			text: text.to_string(),
+			segments: String::new(),
+			penalty,
+		}
+	}
+
+	#[test]
+	fn ranks_fuzzy_matches_by_kind_first() {
+		let full = fuzzy_match("abc", "axbxc");
+		let prefix = fuzzy_match("abc", "abcx");
+		assert_eq!((full.kind, full.penalty), (Kind::Full, 6));
+		assert_eq!((prefix.kind, prefix.penalty), (Kind::Prefix, 1));
+		assert!(full.rank() < prefix.rank());
+	}
+
+	#[test]
+	fn matches_text_kinds() {
+		let check =
+			|key: &str, text: &str| match_text(&chars(key), &chars(text), false).map(|x| x.0);
+		assert_eq!(check("abc", "abc"), Some(Kind::Full));
+		assert_eq!(check("ab", "abc"), Some(Kind::Prefix));
+		assert_eq!(check("bc", "abc"), Some(Kind::Suffix));
+		assert_eq!(check("b", "abc"), Some(Kind::Contains));
+		assert_eq!(check("x", "abc"), None);
+		assert_eq!(check("", "abc"), None);
+	}
+
+	#[test]
+	fn matches_globs() {
+		let check = |pattern: &str, text: &str| match_glob(&chars(pattern), &chars(text));
+		assert!(check("a*", "abc"));
+		assert!(check("*c", "abc"));
+		assert!(check("a?c", "abc"));
+		assert!(check("*b*", "abc"));
+		assert!(check("a**", "a"));
+		assert!(!check("a?", "abc"));
+		assert!(!check("*x*", "abc"));
+	}
+
+	#[test]
+	fn matches_fuzzy() {
+		let check = |key: &str, text: &str| match_fuzzy(&chars(key), &chars(text));
+		assert_eq!(check("ac", "abc"), Some((Kind::Full, 3, vec![0, 2])));
+		assert_eq!(check("ab", "abc"), Some((Kind::Prefix, 1, vec![0, 1])));
+		assert_eq!(check("bd", "abcd"), Some((Kind::Suffix, 4, vec![1, 3])));
+		assert_eq!(
+			check("bcd", "abcde"),
+			Some((Kind::Contains, 2, vec![1, 2, 3]))
+		);
+		assert_eq!(check("ca", "abc"), None);
+	}
+
+	#[test]
+	fn culls_fuzzy_by_penalty() {
+		let check = |key: &str, text: &str| match_fuzzy(&chars(key), &chars(text)).map(|x| x.1);
+		assert_eq!(check("abc", "abxc"), Some(3));
+		assert_eq!(check("abc", "axxbc"), Some(4));
+		assert_eq!(check("abc", "axbxc"), Some(6));
+		assert_eq!(check("abc", "axxbxxc"), None);
+		assert_eq!(check("abc", "abcxxxxxxx"), None);
+		assert_eq!(check("a", "xxa"), Some(2));
+	}
+
+	#[test]
+	fn finds_fuzzy_alignment_with_fewest_gaps() {
+		let check = |key: &str, text: &str| fuzzy_alignment(&chars(key), &chars(text));
+		assert_eq!(check("abc", "abc"), Some((0, vec![0, 1, 2])));
+		assert_eq!(check("abc", "axbcxabc"), Some((0, vec![5, 6, 7])));
+		assert_eq!(check("abc", "axbxc"), Some((2, vec![0, 2, 4])));
+		assert_eq!(check("abc", "abxbc"), Some((1, vec![0, 3, 4])));
+		assert_eq!(check("ab", "aab"), Some((0, vec![1, 2])));
+		assert_eq!(check("ab", "ba"), None);
+	}
+
+	#[test]
+	fn skips_rows_found_in_full_by_index() {
+		let row = IndexRow {
+			entry: 0,
+			text: String::from("らあめん"),
+			exact: chars(&exact_key("らあめん")),
+			approx: chars(&approx_key("らあめん")),
+		};
+		let check = |key: &str, full: Option<Tier>| {
+			let (exact, approx) = (chars(&exact_key(key)), chars(&approx_key(key)));
+			match_row(&row, &exact, &approx, false, Tier::Fuzzy, full).map(|x| (x.0, x.1))
+		};
+		assert_eq!(check("らあめん", Some(Tier::Exact)), None);
+		assert_eq!(check("らーめん", Some(Tier::Approx)), None);
+		assert_eq!(check("らあ", None), Some((Tier::Exact, Kind::Prefix)));
+		assert_eq!(check("らーめ", None), Some((Tier::Approx, Kind::Prefix)));
+	}
+
+	#[test]
+	fn matches_segments_of_text() {
+		let check = |text: &str, key: fn(&str) -> String, positions: &[usize]| {
+			match_segments(text, key, positions)
+		};
+		let pair = |text: &str, segments: &str| (text.to_string(), segments.to_string());
+		assert_eq!(
+			check("タベル", exact_key, &[0, 1, 2]),
+			pair("たべる", "たべる")
+		);
+		assert_eq!(check("食べる", exact_key, &[0]), pair("食べる", "食"));
+		assert_eq!(check("食べる", approx_key, &[1, 2]), pair("食べる", "べる"));
+		assert_eq!(
+			check("ラーメン", approx_key, &[1, 2]),
+			pair("らーめん", "めん")
+		);
+		assert_eq!(check("きょう", approx_key, &[0]), pair("きょう", "き"));
+	}
+
+	#[test]
+	fn maps_glob_keys() {
+		assert_eq!(map_glob("a*b?c", |x| x.to_uppercase()), "A*B?C");
+		assert_eq!(map_glob("?ab*", |x| x.to_uppercase()), "?AB*");
+	}
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::dict::Entry;
+use crate::kana;
+
+/// Search index for the kanji and reading elements of the dictionary entries.
+///
+/// The index keeps the pre-computed keys used by each match mode. Full
+/// matches are looked up by key, while the other kinds of match are a scan
+/// over the rows.
+#[derive(Default)]
+pub struct Index {
+	rows: Vec<IndexRow>,
+
+	/// Rows by their exact key.
+	exact: HashMap<String, Vec<usize>>,
+
+	/// Rows by their approximate key.
+	approx: HashMap<String, Vec<usize>>,
+}
+
+/// Indexed kanji or reading element.
+pub struct IndexRow {
+	/// Index of the entry in the dictionary.
+	pub entry: usize,
+
+	/// Original text for the element.
+	pub text: String,
+
+	/// Key used for exact matching.
+	pub exact: Vec<char>,
+
+	/// Key used for approximate and fuzzy matching.
+	pub approx: Vec<char>,
+}
+
+impl Index {
+	/// Builds the search index for all entries in the dictionary.
+	pub fn build(entries: &[Entry]) -> Index {
+		let start = Instant::now();
+		let mut rows = Vec::new();
+		let mut exact: HashMap<String, Vec<usize>> = HashMap::new();
+		let mut approx: HashMap<String, Vec<usize>> = HashMap::new();
+		for (index, entry) in entries.iter().enumerate() {
+			let kanji = entry.kanji.iter().map(|x| &x.expr);
+			let reading = entry.reading.iter().map(|x| &x.expr);
+			for expr in kanji.chain(reading) {
+				let exact_key = exact_key(expr);
+				let approx_key = approx_key(expr);
+				rows.push(IndexRow {
+					entry: index,
+					text: expr.clone(),
+					exact: exact_key.chars().collect(),
+					approx: approx_key.chars().collect(),
+				});
+				exact.entry(exact_key).or_default().push(rows.len() - 1);
+				approx.entry(approx_key).or_default().push(rows.len() - 1);
+			}
+		}
+		println!(
+			"inf: indexed {} search rows in {:.2?}",
+			rows.len(),
+			start.elapsed()
+		);
+		Index {
+			rows,
+			exact,
+			approx,
+		}
+	}
+
+	pub fn rows(&self) -> &[IndexRow] {
+		&self.rows
+	}
+
+	/// Returns the position of the rows with the given exact key.
+	pub fn find_exact(&self, key: &str) -> &[usize] {
+		self.exact
+			.get(key)
+			.map(|x| x.as_slice())
+			.unwrap_or_default()
+	}
+
+	/// Returns the position of the rows with the given approximate key.
+	pub fn find_approx(&self, key: &str) -> &[usize] {
+		self.approx
+			.get(key)
+			.map(|x| x.as_slice())
+			.unwrap_or_default()
+	}
+}
+
+/// Key used to compare text in exact matches.
+///
+/// Romaji and katakana are converted to hiragana, so they match the same
+/// text, and any remaining letters are compared ignoring case.
+pub fn exact_key(text: &str) -> String {
+	kana::to_hiragana(text).to_uppercase()
+}
+
+/// Key used to compare text in approximate matches.
+///
+/// See [kana::to_hiragana_key] for the normalization rules.
+pub fn approx_key(text: &str) -> String {
+	kana::to_hiragana_key(text)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::dict::{EntryKanji, EntryReading};
+
+	fn entry(kanji: &str, reading: &str) -> Entry {
+		Entry {
+			kanji: vec![EntryKanji {
+				expr: kanji.to_string(),
+				..Default::default()
+			}],
+			reading: vec![EntryReading {
+				expr: reading.to_string(),
+				..Default::default()
+			}],
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn finds_rows_by_key() {
+		let index = Index::build(&[entry("食べる", "たべる"), entry("ラーメン", "らあめん")]);
+		let entries = |rows: &[usize]| -> Vec<usize> {
+			rows.iter().map(|&row| index.rows()[row].entry).collect()
+		};
+		assert_eq!(entries(index.find_exact(&exact_key("taberu"))), vec![0]);
+		assert_eq!(entries(index.find_exact(&exact_key("食べる"))), vec![0]);
+		assert!(index.find_exact(&exact_key("たべ")).is_empty());
+		assert_eq!(entries(index.find_exact(&exact_key("らーめん"))), vec![1]);
+		assert_eq!(
+			entries(index.find_approx(&approx_key("らーめん"))),
+			vec![1, 1]
+		);
+	}
+
+	#[test]
+	fn builds_exact_key() {
+		assert_eq!(exact_key("taberu"), "たべる");
+		assert_eq!(exact_key("TABERU"), "たべる");
+		assert_eq!(exact_key("タベル"), "たべる");
+		assert_eq!(exact_key("食べる"), "食べる");
+		assert_eq!(exact_key("cd"), exact_key("ＣＤ"));
+	}
+}
@@ -0,0 +1,551 @@
+//! Parser for the search query string.
+//!
+//! See the `search` field in the GraphQL schema for the full documentation of
+//! the query syntax. In short:
+//!
+//! - Space separated predicates are combined with an OR.
+//! - `!` negates a full predicate.
+//! - `&` (AND) and `~` (AND NOT) combine terms inside a predicate.
+//! - `=` prefix marks a keyword as exact and `>` enables fuzzy matching.
+//! - `*` and `?` are glob operators inside keywords.
+//! - Parenthesis and square brackets group predicates.
+//! - A standalone `,` matches the entries saved to the word history.
+//!
+//! All operators also accept their fullwidth variants.
+
+use std::fmt;
+
+/// Parsed node for a search query.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+	/// Plain keyword matched against an entry's kanji and readings.
+	Keyword(Keyword),
+
+	/// Matches entries that match any of the expressions. Negated expressions
+	/// take precedence over the positive ones.
+	Or(Vec<Expr>),
+
+	/// Matches entries that match all of the expressions.
+	And(Vec<Expr>),
+
+	/// Matches entries that do not match the expression.
+	Not(Box<Expr>),
+
+	/// Matches entries saved to the word history.
+	History,
+}
+
+impl Expr {
+	/// True if the expression references the word history. Results for those
+	/// expressions change when the history changes.
+	pub fn uses_history(&self) -> bool {
+		match self {
+			Expr::History => true,
+			Expr::Keyword(_) => false,
+			Expr::Or(items) | Expr::And(items) => items.iter().any(|x| x.uses_history()),
+			Expr::Not(expr) => expr.uses_history(),
+		}
+	}
+}
+
+/// Formats the expression back into a query string. The result is normalized
+/// so that equivalent queries have the same text.
+impl fmt::Display for Expr {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Expr::Or(items) => write_list(f, items),
+			expr => write_term(f, expr),
+		}
+	}
+}
+
+fn write_list(f: &mut fmt::Formatter, items: &[Expr]) -> fmt::Result {
+	for (n, it) in items.iter().enumerate() {
+		if n > 0 {
+			write!(f, " ")?;
+		}
+		match it {
+			Expr::Not(expr) => {
+				write!(f, "!")?;
+				write_term(f, expr)?;
+			}
+			expr => write_term(f, expr)?,
+		}
+	}
+	Ok(())
+}
+
+fn write_term(f: &mut fmt::Formatter, expr: &Expr) -> fmt::Result {
+	match expr {
+		Expr::Keyword(Keyword { text, mode }) => match mode {
+			KeywordMode::Default => write!(f, "{}", text),
+			KeywordMode::Exact => write!(f, "={}", text),
+			KeywordMode::Fuzzy => write!(f, ">{}", text),
+		},
+		Expr::History => write!(f, "{}", HISTORY),
+		Expr::And(items) => {
+			for (n, it) in items.iter().enumerate() {
+				match it {
+					Expr::Not(expr) => {
+						write!(f, "~")?;
+						write_group(f, expr)?;
+					}
+					expr => {
+						if n > 0 {
+							write!(f, "&")?;
+						}
+						write_group(f, expr)?;
+					}
+				}
+			}
+			Ok(())
+		}
+		expr => write_group(f, expr),
+	}
+}
+
+fn write_group(f: &mut fmt::Formatter, expr: &Expr) -> fmt::Result {
+	match expr {
+		Expr::Or(items) => {
+			write!(f, "(")?;
+			write_list(f, items)?;
+			write!(f, ")")
+		}
+		Expr::Not(_) | Expr::And(_) => {
+			write!(f, "(")?;
+			write_list(f, std::slice::from_ref(expr))?;
+			write!(f, ")")
+		}
+		expr => write_term(f, expr),
+	}
+}
+
+/// Keyword in a search query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keyword {
+	/// Text for the keyword. Glob operators are normalized to `*` and `?`.
+	pub text: String,
+
+	/// Matching mode for the keyword.
+	pub mode: KeywordMode,
+}
+
+impl Keyword {
+	/// True if the keyword text contains glob operators.
+	pub fn has_glob(&self) -> bool {
+		self.text.contains(&[GLOB_ANY, GLOB_ONE][..])
+	}
+}
+
+/// Matching mode for a [Keyword].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeywordMode {
+	/// Matches exactly and approximately.
+	Default,
+
+	/// Matches only exactly (`=` prefix).
+	Exact,
+
+	/// Also enables fuzzy matching (`>` prefix).
+	Fuzzy,
+}
+
+/// Error parsing a search query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+	/// Character offset of the error in the query string.
+	pub position: usize,
+
+	/// Error description.
+	pub message: String,
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} at position {}", self.message, self.position)
+	}
+}
+
+impl std::error::Error for ParseError {}
+
+/// Glob operator matching zero or more characters.
+pub const GLOB_ANY: char = '*';
+
+/// Glob operator matching a single character.
+pub const GLOB_ONE: char = '?';
+
+/// Standalone keyword matching the word history.
+pub const HISTORY: char = ',';
+
+/// Parses a query string. The result is always an [Expr::Or] with the list of
+/// top-level predicates, which can be empty.
+pub fn parse(query: &str) -> Result<Expr, ParseError> {
+	let mut parser = Parser {
+		chars: query.chars().collect(),
+		offset: 0,
+	};
+	let predicates = parser.parse_list(None)?;
+	Ok(Expr::Or(predicates))
+}
+
+struct Parser {
+	chars: Vec<char>,
+	offset: usize,
+}
+
+impl Parser {
+	/// Parses a list of space separated predicates until the end of the input
+	/// or the given closing character.
+	fn parse_list(&mut self, close: Option<(char, usize)>) -> Result<Vec<Expr>, ParseError> {
+		let mut list = Vec::new();
+		loop {
+			self.skip_spaces();
+			match self.peek() {
+				None => {
+					if let Some((chr, position)) = close {
+						let message = format!("unclosed group, expected '{}'", chr);
+						return Err(ParseError { position, message });
+					}
+					break;
+				}
+				Some(chr) if is_group_close(chr) => match close {
+					Some((expected, _)) if close_char(chr) == expected => {
+						self.offset += 1;
+						break;
+					}
+					_ => return Err(self.error(format!("unexpected '{}'", chr))),
+				},
+				Some(_) => list.push(self.parse_predicate()?),
+			}
+		}
+		Ok(list)
+	}
+
+	/// Parses a predicate, which may be negated.
+	fn parse_predicate(&mut self) -> Result<Expr, ParseError> {
+		if let Some(chr) = self.peek().filter(|&c| op(c) == Some(Op::Not)) {
+			self.offset += 1;
+			self.expect_term(chr)?;
+			let expr = self.parse_and()?;
+			Ok(Expr::Not(Box::new(expr)))
+		} else {
+			self.parse_and()
+		}
+	}
+
+	/// Parses a sequence of terms combined with the `&` and `~` operators.
+	fn parse_and(&mut self) -> Result<Expr, ParseError> {
+		let mut terms = vec![self.parse_term()?];
+		while let Some(chr) = self.peek() {
+			let negate = match op(chr) {
+				Some(Op::And) => false,
+				Some(Op::AndNot) => true,
+				_ => break,
+			};
+			self.offset += 1;
+			self.expect_term(chr)?;
+			let term = self.parse_term()?;
+			terms.push(if negate {
+				Expr::Not(Box::new(term))
+			} else {
+				term
+			});
+		}
+		Ok(if terms.len() == 1 {
+			terms.pop().unwrap()
+		} else {
+			Expr::And(terms)
+		})
+	}
+
+	/// Parses a single keyword or group.
+	fn parse_term(&mut self) -> Result<Expr, ParseError> {
+		let start = self.offset;
+		let chr = match self.peek() {
+			Some(chr) => chr,
+			None => return Err(self.error("expected keyword")),
+		};
+
+		if is_group_open(chr) {
+			self.offset += 1;
+			let mut list = self.parse_list(Some((close_char(chr), start)))?;
+			return match list.len() {
+				0 => Err(ParseError {
+					position: start,
+					message: String::from("empty group"),
+				}),
+				1 => Ok(list.pop().unwrap()),
+				_ => Ok(Expr::Or(list)),
+			};
+		}
+
+		if op(chr) == Some(Op::History) && self.at_term_end(self.offset + 1) {
+			self.offset += 1;
+			return Ok(Expr::History);
+		}
+
+		let mode = match op(chr) {
+			Some(Op::Exact) => KeywordMode::Exact,
+			Some(Op::Fuzzy) => KeywordMode::Fuzzy,
+			_ => KeywordMode::Default,
+		};
+		if mode != KeywordMode::Default {
+			self.offset += 1;
+		}
+
+		let mut text = String::new();
+		while !self.at_term_end(self.offset) {
+			let chr = self.chars[self.offset];
+			match op(chr) {
+				Some(Op::GlobAny) => text.push(GLOB_ANY),
+				Some(Op::GlobOne) => text.push(GLOB_ONE),
+				_ => text.push(chr),
+			}
+			self.offset += 1;
+		}
+
+		if text.is_empty() {
+			let message = if mode != KeywordMode::Default {
+				format!("expected keyword after '{}'", chr)
+			} else {
+				format!("unexpected '{}'", self.peek().unwrap_or(chr))
+			};
+			return Err(self.error(message));
+		}
+
+		Ok(Expr::Keyword(Keyword { text, mode }))
+	}
+
+	/// Checks that the operator is followed by a term.
+	fn expect_term(&self, operator: char) -> Result<(), ParseError> {
+		match self.peek() {
+			Some(chr) if !is_space(chr) && !is_group_close(chr) => Ok(()),
+			_ => Err(self.error(format!("expected keyword after '{}'", operator))),
+		}
+	}
+
+	/// True if the offset is at the end of a term.
+	fn at_term_end(&self, offset: usize) -> bool {
+		match self.chars.get(offset).cloned() {
+			None => true,
+			Some(chr) => {
+				is_space(chr)
+					|| is_group_open(chr)
+					|| is_group_close(chr)
+					|| matches!(op(chr), Some(Op::And) | Some(Op::AndNot))
+			}
+		}
+	}
+
+	fn skip_spaces(&mut self) {
+		while self.peek().map(is_space).unwrap_or(false) {
+			self.offset += 1;
+		}
+	}
+
+	fn peek(&self) -> Option<char> {
+		self.chars.get(self.offset).cloned()
+	}
+
+	fn error<S: Into<String>>(&self, message: S) -> ParseError {
+		ParseError {
+			position: self.offset,
+			message: message.into(),
+		}
+	}
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Op {
+	Not,
+	And,
+	AndNot,
+	Exact,
+	Fuzzy,
+	GlobAny,
+	GlobOne,
+	History,
+}
+
+fn op(chr: char) -> Option<Op> {
+	let op = match chr {
+		'!' | '！' => Op::Not,
+		'&' | '＆' => Op::And,
+		'~' | '～' => Op::AndNot,
+		'=' | '＝' => Op::Exact,
+		'>' | '＞' => Op::Fuzzy,
+		'*' | '＊' => Op::GlobAny,
+		'?' | '？' => Op::GlobOne,
+		',' | '，' | '、' => Op::History,
+		_ => return None,
+	};
+	Some(op)
+}
+
+fn is_space(chr: char) -> bool {
+	chr.is_whitespace()
+}
+
+fn is_group_open(chr: char) -> bool {
+	matches!(chr, '(' | '[' | '（' | '［')
+}
+
+fn is_group_close(chr: char) -> bool {
+	matches!(chr, ')' | ']' | '）' | '］')
+}
+
+/// Normalized closing character for a group delimiter.
+fn close_char(chr: char) -> char {
+	match chr {
+		'(' | ')' | '（' | '）' => ')',
+		_ => ']',
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn kw(text: &str) -> Expr {
+		Expr::Keyword(Keyword {
+			text: text.to_string(),
+			mode: KeywordMode::Default,
+		})
+	}
+
+	fn kw_mode(text: &str, mode: KeywordMode) -> Expr {
+		Expr::Keyword(Keyword {
+			text: text.to_string(),
+			mode,
+		})
+	}
+
+	fn not(expr: Expr) -> Expr {
+		Expr::Not(Box::new(expr))
+	}
+
+	fn check(query: &str, expected: Vec<Expr>) {
+		assert_eq!(parse(query), Ok(Expr::Or(expected)), "query: {}", query);
+	}
+
+	fn check_error(query: &str, position: usize) {
+		match parse(query) {
+			Ok(expr) => panic!("query `{}` should fail, parsed as {:?}", query, expr),
+			Err(err) => assert_eq!(
+				err.position, position,
+				"query `{}` failed with: {}",
+				query, err
+			),
+		}
+	}
+
+	#[test]
+	fn parses_empty_query() {
+		check("", vec![]);
+		check("  \u{3000} ", vec![]);
+	}
+
+	#[test]
+	fn parses_keywords_as_or() {
+		check("abc", vec![kw("abc")]);
+		check(
+			" abc  def\u{3000}ghi ",
+			vec![kw("abc"), kw("def"), kw("ghi")],
+		);
+	}
+
+	#[test]
+	fn parses_keyword_modes() {
+		check("=abc", vec![kw_mode("abc", KeywordMode::Exact)]);
+		check("＞abc", vec![kw_mode("abc", KeywordMode::Fuzzy)]);
+		check("a=b", vec![kw("a=b")]);
+	}
+
+	#[test]
+	fn parses_globs() {
+		check("a*b?", vec![kw("a*b?")]);
+		check("a＊b？", vec![kw("a*b?")]);
+		let keyword = Keyword {
+			text: "a*".to_string(),
+			mode: KeywordMode::Default,
+		};
+		assert!(keyword.has_glob());
+	}
+
+	#[test]
+	fn parses_and_operators() {
+		check(
+			"a&b~c",
+			vec![Expr::And(vec![kw("a"), kw("b"), not(kw("c"))])],
+		);
+		check(
+			"a＆=b～c",
+			vec![Expr::And(vec![
+				kw("a"),
+				kw_mode("b", KeywordMode::Exact),
+				not(kw("c")),
+			])],
+		);
+	}
+
+	#[test]
+	fn parses_negation() {
+		check("!a b", vec![not(kw("a")), kw("b")]);
+		check("！a&b", vec![not(Expr::And(vec![kw("a"), kw("b")]))]);
+		check("a!", vec![kw("a!")]);
+	}
+
+	#[test]
+	fn parses_groups() {
+		check(
+			"(a b)&c",
+			vec![Expr::And(vec![Expr::Or(vec![kw("a"), kw("b")]), kw("c")])],
+		);
+		check("[a]", vec![kw("a")]);
+		check(
+			"!（a ［b c］）",
+			vec![not(Expr::Or(vec![
+				kw("a"),
+				Expr::Or(vec![kw("b"), kw("c")]),
+			]))],
+		);
+		check("a(b)", vec![kw("a"), kw("b")]);
+	}
+
+	#[test]
+	fn parses_history() {
+		check(", a", vec![Expr::History, kw("a")]);
+		check("、&a", vec![Expr::And(vec![Expr::History, kw("a")])]);
+		check("a,b ,,", vec![kw("a,b"), kw(",,")]);
+		assert!(parse("a (!，)").unwrap().uses_history());
+		assert!(!parse("a,b").unwrap().uses_history());
+	}
+
+	#[test]
+	fn formats_normalized_query() {
+		let check =
+			|query: &str, expected: &str| assert_eq!(parse(query).unwrap().to_string(), expected);
+		check("  a\u{3000}b ", "a b");
+		check("！a＆=b～c ＞d", "!a&=b~c >d");
+		check("[a (b)]&!c", "(a b)&!c");
+		check("(a&b)~(c d) !(e f)", "(a&b)~(c d) !(e f)");
+		check("a&(!b) 、", "a~b ,");
+		for query in ["(a b)&!c", "a&b~(c d) !(e f)", "a~b ,", "!a&b", "a&(b&c)"].iter() {
+			let expr = parse(query).unwrap();
+			assert_eq!(parse(&expr.to_string()).unwrap(), expr, "query: {}", query);
+		}
+	}
+
+	#[test]
+	fn reports_error_positions() {
+		check_error("(a b", 0);
+		check_error("a (b c]", 6);
+		check_error("a )", 2);
+		check_error("()", 0);
+		check_error("= a", 1);
+		check_error("a& b", 2);
+		check_error("a~", 2);
+		check_error("! a", 1);
+		check_error("a &b", 2);
+	}
+}
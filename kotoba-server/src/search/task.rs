@@ -0,0 +1,109 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::runtime::Handle;
+use tokio::sync::Notify;
+
+use super::engine::{Engine, Match};
+use super::query::Expr;
+
+/// Search running in the background.
+///
+/// All tiers are evaluated in a single pass over the index, so results
+/// become available once the search completes.
+pub struct Search {
+	query: String,
+	start: Instant,
+	state: Mutex<State>,
+	updated: Notify,
+}
+
+#[derive(Default)]
+struct State {
+	rows: Vec<Match>,
+	elapsed: Option<Duration>,
+}
+
+impl Search {
+	/// Starts a new search for the expression as a task in the given runtime.
+	pub fn spawn(
+		runtime: &Handle,
+		engine: Engine<'static>,
+		query: &str,
+		expr: Expr,
+	) -> Arc<Search> {
+		let search = Arc::new(Search {
+			query: query.to_string(),
+			start: Instant::now(),
+			state: Default::default(),
+			updated: Notify::new(),
+		});
+
+		let output = search.clone();
+		runtime.spawn(async move {
+			let rows = tokio::task::spawn_blocking(move || engine.search(&expr)).await;
+			match rows {
+				Ok(rows) => output.push(rows),
+				Err(err) => eprintln!("err: search `{}` failed: {}", output.query, err),
+			}
+			output.complete();
+		});
+		search
+	}
+
+	/// Query string for the search.
+	pub fn query(&self) -> &str {
+		&self.query
+	}
+
+	/// True while the search is still running in the background.
+	pub fn loading(&self) -> bool {
+		self.state.lock().unwrap().elapsed.is_none()
+	}
+
+	/// Number of results loaded. For a running search this is a partial count.
+	pub fn total(&self) -> usize {
+		self.state.lock().unwrap().rows.len()
+	}
+
+	/// Elapsed time for the search. For a running search this is the partial
+	/// running time.
+	pub fn elapsed(&self) -> Duration {
+		match self.state.lock().unwrap().elapsed {
+			Some(elapsed) => elapsed,
+			None => self.start.elapsed(),
+		}
+	}
+
+	/// Returns a page from the results.
+	///
+	/// This waits until rows are available at the page offset or the search
+	/// completes. The page may not be complete if the search is still loading.
+	pub async fn page(&self, offset: usize, limit: usize) -> Vec<Match> {
+		loop {
+			let updated = self.updated.notified();
+			{
+				let state = self.state.lock().unwrap();
+				let rows = &state.rows;
+				if state.elapsed.is_some() || offset < rows.len() || limit == 0 {
+					let sta = offset.min(rows.len());
+					let end = offset.saturating_add(limit).min(rows.len());
+					return rows[sta..end].to_vec();
+				}
+			}
+			updated.await;
+		}
+	}
+
+	fn push(&self, rows: Vec<Match>) {
+		if !rows.is_empty() {
+			self.state.lock().unwrap().rows.extend(rows);
+			self.updated.notify_waiters();
+		}
+	}
+
+	fn complete(&self) {
+		self.state.lock().unwrap().elapsed = Some(self.start.elapsed());
+		self.updated.notify_waiters();
+	}
+}
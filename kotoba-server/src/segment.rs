@@ -0,0 +1,394 @@
+//! Segmentation of a text into dictionary words.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use crate::dict::Dict;
+use crate::inflection::{self, Deinflector, Rules};
+use crate::kana;
+
+/// Maximum length of a word, in characters.
+pub const MAX_WORD_LENGTH: usize = 20;
+
+/// Maximum length of a text for [segment], in characters. Longer texts are
+/// not matched against the dictionary, since that is too expensive.
+const MAX_TEXT_LENGTH: usize = 500;
+
+/// Base cost for each word in the segmentation. Since this is paid per word,
+/// segmentations with fewer and longer words are favored.
+const WORD_COST: f64 = 1.0;
+
+/// Cost for each character not covered by a word. This is higher than any
+/// word, so that covering the text is always favored.
+const UNKNOWN_COST: f64 = 2.0;
+
+/// Segment of the text.
+#[derive(Clone, Debug)]
+pub struct Segment<'a> {
+	/// Position of the segment in the text, in characters.
+	pub position: usize,
+
+	/// Text for the segment.
+	pub text: String,
+
+	/// All words starting at the segment position. The word for the segment
+	/// text is first, then the others from longest to shortest.
+	///
+	/// This is empty for text not covered by any word.
+	pub words: Vec<Word<'a>>,
+}
+
+/// Dictionary word, possibly inflected, in the text.
+#[derive(Clone, Debug)]
+pub struct Word<'a> {
+	/// Text for the word, as it appears in the text.
+	pub text: String,
+
+	/// Cost of the word for the segmentation. Lower is better.
+	pub cost: f64,
+
+	/// Entries matching the word.
+	pub entries: Vec<inflection::Match<'a>>,
+}
+
+impl<'a> Word<'a> {
+	fn new(text: String, entries: Vec<inflection::Match<'a>>) -> Word<'a> {
+		// More frequent words have lower cost. The entry position is a good
+		// proxy for frequency, since popular entries come first.
+		let position = entries.iter().map(|x| x.entry.position).min().unwrap_or(1);
+		let cost = WORD_COST + (position.max(1) as f64).log10() / 10.0;
+		Word {
+			text,
+			cost,
+			entries,
+		}
+	}
+
+	fn len(&self) -> usize {
+		self.text.chars().count()
+	}
+}
+
+/// Splits the text into words, choosing the segmentation covering the text
+/// with the lowest cost.
+///
+/// Words are matched at every position of the text, including inflected
+/// words. Each segment contains all the alternative words at its position.
+///
+/// A text over [MAX_TEXT_LENGTH] is returned as a single segment without
+/// words.
+pub fn segment<'a>(dict: &'a Dict, rules: &Rules, text: &str) -> Vec<Segment<'a>> {
+	let chars: Vec<char> = text.chars().collect();
+
+	// Find all words starting at each position.
+	let mut deinflector = Deinflector::new(rules);
+	let mut words: Vec<Vec<Word>> = vec![Vec::new(); chars.len()];
+	if chars.len() <= MAX_TEXT_LENGTH {
+		for (position, list) in words.iter_mut().enumerate() {
+			let max_end = std::cmp::min(chars.len(), position + MAX_WORD_LENGTH);
+			for end in (position + 1..=max_end).rev() {
+				let text: String = chars[position..end].iter().collect();
+				deinflector.add(&text, false);
+				let entries = deinflector.lookup(dict, &text, position, false);
+				if !entries.is_empty() {
+					list.push(Word::new(text, entries));
+				}
+			}
+		}
+	}
+
+	let path = best_path(&words, chars.len());
+
+	let mut output: Vec<Segment> = Vec::new();
+	for (position, word) in path {
+		let mut list = std::mem::take(&mut words[position]);
+		let text = match word {
+			Some(index) => {
+				let word = list.remove(index);
+				let text = word.text.clone();
+				list.insert(0, word);
+				text
+			}
+			None => chars[position].to_string(),
+		};
+
+		// Merge sequences of unknown characters.
+		if let Some(last) = output.last_mut() {
+			if word.is_none() && list.is_empty() && last.words.is_empty() {
+				last.text.push_str(&text);
+				continue;
+			}
+		}
+
+		output.push(Segment {
+			position,
+			text,
+			words: list,
+		});
+	}
+
+	output
+}
+
+/// Words matched at an offset of the text.
+#[derive(Clone, Debug)]
+pub struct Scan<'a> {
+	/// Start of the matched range in the text, in characters.
+	pub start: usize,
+
+	/// End of the matched range in the text, in characters. This is the end
+	/// of the longest match.
+	pub end: usize,
+
+	/// Text for the matched range.
+	pub text: String,
+
+	/// Entries matching at the offset, including de-inflected matches.
+	pub entries: Vec<inflection::Match<'a>>,
+}
+
+/// Looks up the dictionary words starting at the offset of the text, trying
+/// every length up to `max_length` characters.
+///
+/// The text is converted to hiragana for the lookup, so katakana and romaji
+/// also match. The matched range is still relative to the original text.
+///
+/// Entries are sorted like Yomichan: longer matches first, then the least
+/// number of de-inflection rules, then the most popular entries. Each entry
+/// appears only once, for its best match.
+pub fn scan<'a>(
+	dict: &'a Dict,
+	rules: &Rules,
+	text: &str,
+	offset: usize,
+	max_length: usize,
+) -> Scan<'a> {
+	let chars: Vec<char> = text.chars().skip(offset).take(max_length).collect();
+
+	let mut deinflector = Deinflector::new(rules);
+	let mut matches = Vec::new();
+	for length in (1..=chars.len()).rev() {
+		let query: String = chars[..length].iter().collect();
+		let term = kana::to_hiragana(&query);
+		deinflector.add(&term, false);
+		for mut m in deinflector.filter_term(dict, &term, false) {
+			m.query = query.clone();
+			matches.push(m);
+		}
+	}
+
+	scan_matches(&chars, offset, matches)
+}
+
+/// Builds the [Scan] for the matches at the offset of the text, keeping only
+/// the best ranked match for each entry. The `chars` start at the offset.
+fn scan_matches<'a>(
+	chars: &[char],
+	offset: usize,
+	matches: Vec<inflection::Match<'a>>,
+) -> Scan<'a> {
+	let rank = |m: &inflection::Match| {
+		(
+			Reverse(m.query.chars().count()),
+			m.rules.len(),
+			m.entry.position,
+		)
+	};
+
+	let mut best: HashMap<usize, inflection::Match> = HashMap::new();
+	for mut m in matches {
+		m.position = offset;
+		match best.get(&m.entry.position) {
+			Some(cur) if rank(cur) <= rank(&m) => {}
+			_ => {
+				best.insert(m.entry.position, m);
+			}
+		}
+	}
+	let mut entries: Vec<inflection::Match> = best.into_values().collect();
+	entries.sort_by_key(|m| rank(m));
+
+	let length = entries
+		.first()
+		.map(|m| m.query.chars().count())
+		.unwrap_or(0);
+	Scan {
+		start: offset,
+		end: offset + length,
+		text: chars[..length].iter().collect(),
+		entries,
+	}
+}
+
+/// Finds the lowest cost path covering the text. Returns the position of each
+/// step and the index of the word, or [None] for an unknown character.
+fn best_path(words: &[Vec<Word>], length: usize) -> Vec<(usize, Option<usize>)> {
+	// Lowest cost to cover the text up to each position, and the previous
+	// step in the path.
+	let mut cost = vec![f64::INFINITY; length + 1];
+	let mut prev: Vec<(usize, Option<usize>)> = vec![(0, None); length + 1];
+	cost[0] = 0.0;
+	for position in 0..length {
+		let base = cost[position];
+		let mut relax = |end: usize, value: f64, word: Option<usize>| {
+			if base + value < cost[end] {
+				cost[end] = base + value;
+				prev[end] = (position, word);
+			}
+		};
+		relax(position + 1, UNKNOWN_COST, None);
+		for (index, word) in words[position].iter().enumerate() {
+			relax(position + word.len(), word.cost, Some(index));
+		}
+	}
+
+	let mut path = Vec::new();
+	let mut end = length;
+	while end > 0 {
+		let step = prev[end];
+		path.push(step);
+		end = step.0;
+	}
+	path.reverse();
+	path
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::dict::{Entry, EntryReading};
+
+	fn word(text: &str, cost: f64) -> Word<'static> {
+		Word {
+			text: text.to_string(),
+			cost,
+			entries: Vec::new(),
+		}
+	}
+
+	fn entry(position: usize) -> Entry {
+		Entry {
+			position,
+			..Default::default()
+		}
+	}
+
+	fn word_entry(position: usize, reading: &str) -> Entry {
+		Entry {
+			position,
+			reading: vec![EntryReading {
+				expr: reading.to_string(),
+				..Default::default()
+			}],
+			..Default::default()
+		}
+	}
+
+	fn scan_match<'a>(entry: &'a Entry, query: &str, rules: &[&str]) -> inflection::Match<'a> {
+		inflection::Match {
+			entry,
+			query: query.to_string(),
+			position: 0,
+			text: query.to_string(),
+			prefix: String::new(),
+			suffix: String::new(),
+			rules: rules.iter().map(|x| x.to_string()).collect(),
+		}
+	}
+
+	#[test]
+	fn scans_best_match_for_each_entry() {
+		let entries = [entry(1), entry(2), entry(3)];
+		let matches = vec![
+			scan_match(&entries[0], "ab", &["x", "y"]),
+			scan_match(&entries[0], "ab", &["x"]),
+			scan_match(&entries[1], "a", &[]),
+			scan_match(&entries[1], "abc", &["x"]),
+			scan_match(&entries[2], "ab", &[]),
+		];
+		let chars: Vec<char> = "abcd".chars().collect();
+		let scan = scan_matches(&chars, 5, matches);
+		assert_eq!((scan.start, scan.end), (5, 8));
+		assert_eq!(scan.text, "abc");
+
+		let list: Vec<(usize, &str, usize)> = scan
+			.entries
+			.iter()
+			.map(|m| (m.entry.position, m.query.as_str(), m.rules.len()))
+			.collect();
+		assert_eq!(list, vec![(2, "abc", 1), (3, "ab", 0), (1, "ab", 1)]);
+		assert!(scan.entries.iter().all(|m| m.position == 5));
+	}
+
+	#[test]
+	fn scans_without_matches() {
+		let chars: Vec<char> = "abcd".chars().collect();
+		let scan = scan_matches(&chars, 2, Vec::new());
+		assert_eq!((scan.start, scan.end), (2, 2));
+		assert_eq!(scan.text, "");
+		assert!(scan.entries.is_empty());
+	}
+
+	#[test]
+	fn finds_path_with_fewer_words() {
+		let words = vec![
+			vec![word("abc", 1.0), word("ab", 1.0), word("a", 1.0)],
+			vec![],
+			vec![word("cd", 1.0), word("c", 1.0)],
+			vec![word("d", 1.0)],
+		];
+		assert_eq!(best_path(&words, 4), vec![(0, Some(1)), (2, Some(0))]);
+	}
+
+	#[test]
+	fn finds_path_with_lower_cost() {
+		let words = vec![
+			vec![word("ab", 1.5), word("a", 1.0)],
+			vec![word("bc", 1.0), word("b", 1.0)],
+			vec![word("c", 1.0)],
+		];
+		assert_eq!(best_path(&words, 3), vec![(0, Some(1)), (1, Some(0))]);
+	}
+
+	#[test]
+	fn skips_unknown_characters() {
+		let words = vec![vec![word("a", 1.0)], vec![], vec![word("c", 1.0)]];
+		assert_eq!(
+			best_path(&words, 3),
+			vec![(0, Some(0)), (1, None), (2, Some(0))]
+		);
+		assert_eq!(best_path(&[vec![], vec![]], 2), vec![(0, None), (1, None)]);
+	}
+
+	#[test]
+	fn scans_katakana_and_romaji() {
+		let dict = Dict::from_entries(vec![word_entry(1, "たべる")]);
+		let rules = Rules::default();
+		let check = |text: &str, offset: usize| {
+			let scan = scan(&dict, &rules, text, offset, MAX_WORD_LENGTH);
+			let queries: Vec<String> = scan.entries.iter().map(|x| x.query.clone()).collect();
+			(scan.start, scan.end, scan.text, queries)
+		};
+		let result = |start: usize, end: usize, text: &str| {
+			(start, end, text.to_string(), vec![text.to_string()])
+		};
+		assert_eq!(check("xタベルよ", 1), result(1, 4, "タベル"));
+		assert_eq!(check("taberu", 0), result(0, 6, "taberu"));
+		assert_eq!(check("たべる", 0), result(0, 3, "たべる"));
+	}
+
+	#[test]
+	fn skips_words_for_long_text() {
+		let dict = Dict::from_entries(vec![word_entry(1, "ねこ")]);
+		let rules = Rules::default();
+		let segments = segment(&dict, &rules, "ねこねこ");
+		assert_eq!(segments.len(), 2);
+		assert_eq!(segments[0].words[0].text, "ねこ");
+
+		let text = "ねこ".repeat(MAX_TEXT_LENGTH);
+		let segments = segment(&dict, &rules, &text);
+		assert_eq!(segments.len(), 1);
+		assert_eq!(segments[0].text, text);
+		assert!(segments[0].words.is_empty());
+	}
+}
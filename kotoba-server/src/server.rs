@@ -3,6 +3,7 @@ use actix_web as web;
 use crate::app::App;
 use crate::graph;
 use crate::graphql;
+use crate::pitch;
 
 #[get("/")]
 async fn hello() -> impl web::Responder {
@@ -26,7 +27,8 @@ pub async fn launch<A: std::net::ToSocketAddrs>(
 				web::web::scope("/api")
 					.service(graphql::ide)
 					.service(graphql::query)
-					.service(graphql::query_get),
+					.service(graphql::query_get)
+					.service(pitch::svg),
 			)
 	})
 	.bind(bind_addr)?